@@ -5,11 +5,14 @@ use crate::{
         client::ChatClient,
         message::{MessageType, ServerMsg, UserMsg},
         server::ChatServer,
-        User,
+        Presence, User,
     },
-    schema::{Color, Config, RoomHeader, ServerRoom},
-    tui::chat_app::ChatApp,
-    util::{create_env_dir, get_unique_id, passwd_input, setup_logger},
+    schema::{
+        Color, Config, DesktopNotify, IgnoredUser, KeymapPreset, RoomHeader, ServerRoom,
+        DEFAULT_AWAY_AFTER, DEFAULT_MAX_FILE_SIZE,
+    },
+    tui::{chat_app::ChatApp, keymap},
+    util::{create_env_dir, get_unique_id, is_valid_username, passwd_input, setup_logger},
 };
 use clap::{Arg, ArgMatches, Command};
 use crossterm::style::Stylize;
@@ -24,6 +27,7 @@ use std::{
 };
 use tokio::time::sleep;
 use tokio_util::either::Either;
+use uuid::Uuid;
 
 pub async fn run(cmd_req: CommandRequest) -> Result<(), AppError> {
     let path = create_env_dir("nosignal")?;
@@ -46,6 +50,8 @@ async fn run_option(cmd_req: CommandRequest, db: Arc<Mutex<DbRepo>>) -> Result<(
         CommandRequest::Delete { room_id } => delete_room(&db.lock().unwrap(), &room_id)?,
         CommandRequest::List => list_rooms_and_config(&db.lock().unwrap())?,
         CommandRequest::Set { option, value } => set_config(&db.lock().unwrap(), &option, &value)?,
+        CommandRequest::Ignore { username } => ignore_user(&db.lock().unwrap(), &username)?,
+        CommandRequest::Unignore { username } => unignore_user(&db.lock().unwrap(), &username)?,
         CommandRequest::Invalid => return Err(AppError::InvalidCommand),
     }
     Ok(())
@@ -56,11 +62,34 @@ pub fn db_init(db_path: &Path) -> pdbResult<DbRepo> {
 
     if db.local_data.count_documents()? == 0 {
         db.local_data.insert_one(Config {
+            uid: Uuid::new_v4().to_string(),
             username: get_unique_id(),
             listener_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 12345),
             color: Color::White,
             light_mode: false,
+            away_after: DEFAULT_AWAY_AFTER,
+            read_receipts: false,
+            highlight_words: vec![],
+            bell: true,
+            desktop_notify: DesktopNotify::Off,
+            ignore_placeholder: true,
+            download_dir: None,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            keep_files: false,
+            markdown: true,
+            keymap: KeymapPreset::Default,
+            key_bindings: vec![],
+            mouse: false,
         })?;
+    } else if db
+        .local_data
+        .find_one(doc! {})?
+        .is_some_and(|config| config.uid.is_empty())
+    {
+        db.local_data.update_one(
+            doc! {},
+            doc! {"$set": doc! {"uid": Uuid::new_v4().to_string()}},
+        )?;
     }
     Ok(db)
 }
@@ -82,6 +111,9 @@ fn create_room(db: &DbRepo, room_id: &str, password: bool) -> Result<(), AppErro
         addr,
         passwd,
         banned_addrs: vec![],
+        topic: None,
+        motd: None,
+        pins: vec![],
     })?;
     Ok(())
 }
@@ -114,11 +146,24 @@ fn list_rooms_and_config(db: &DbRepo) -> Result<(), AppError> {
         .find_one(doc! {})?
         .ok_or(AppError::DataNotFound)?;
     let local_data_print = format!(
-        "Config:\n username: {}\n listener_addr: {}\n color: {}\n light_mode: {}",
+        "Config:\n username: {}\n listener_addr: {}\n color: {}\n light_mode: {}\n away_after: {}\n read_receipts: {}\n highlight_words: {}\n bell: {}\n desktop_notify: {}\n ignore_placeholder: {}\n download_dir: {}\n max_file_size: {} MB\n keep_files: {}\n markdown: {}\n keymap: {}\n key_bindings: {}\n mouse: {}",
         local_data.username,
         local_data.listener_addr.to_string(),
         local_data.color.to_string(),
         local_data.light_mode.to_string(),
+        local_data.away_after,
+        local_data.read_receipts,
+        local_data.highlight_words.join(","),
+        local_data.bell,
+        local_data.desktop_notify,
+        local_data.ignore_placeholder,
+        local_data.download_dir.unwrap_or("default".to_string()),
+        local_data.max_file_size,
+        local_data.keep_files,
+        local_data.markdown,
+        local_data.keymap,
+        local_data.key_bindings.join(","),
+        local_data.mouse,
     );
     println!("{}", local_data_print);
 
@@ -133,9 +178,57 @@ fn list_rooms_and_config(db: &DbRepo) -> Result<(), AppError> {
         let room = el.unwrap();
         println!(" {}: {} [guest]", room._id, room.addr.to_string());
     });
+
+    println!("\nIgnored users:");
+    let ignored_users = db.ignored_users.find(doc! {}).run()?;
+    ignored_users.for_each(|el| {
+        let ignored_user = el.unwrap();
+        if ignored_user.username.is_empty() {
+            println!(" {}", ignored_user._id);
+        } else {
+            println!(" {} ({})", ignored_user.username, ignored_user._id);
+        }
+    });
+    Ok(())
+}
+
+fn ignore_user(db: &DbRepo, username: &str) -> Result<(), AppError> {
+    let (uid, username) = match db.messages.find_one(doc! {"last_username": username})? {
+        Some(msg) if !msg.sender_uid.is_empty() => (msg.sender_uid, msg.last_username),
+        _ if Uuid::parse_str(username).is_ok() => (username.to_string(), String::new()),
+        _ => return Err(AppError::UnknownUser),
+    };
+    if db.ignored_users.find_one(doc! {"_id": &uid})?.is_some() {
+        return Err(AppError::AlreadyExistingId);
+    }
+
+    db.ignored_users
+        .insert_one(IgnoredUser { _id: uid, username })?;
     Ok(())
 }
 
+fn unignore_user(db: &DbRepo, username: &str) -> Result<(), AppError> {
+    if db
+        .ignored_users
+        .delete_one(doc! {"_id": username})?
+        .deleted_count
+        > 0
+    {
+        return Ok(());
+    }
+
+    if db
+        .ignored_users
+        .delete_one(doc! {"username": username})?
+        .deleted_count
+        > 0
+    {
+        return Ok(());
+    }
+
+    Err(AppError::DataNotFound)
+}
+
 async fn join_room(id_or_addr: IdOrAddr, db: Arc<Mutex<DbRepo>>) -> Result<(), AppError> {
     let config = db
         .lock()
@@ -146,8 +239,11 @@ async fn join_room(id_or_addr: IdOrAddr, db: Arc<Mutex<DbRepo>>) -> Result<(), A
         .unwrap();
     let user = User {
         id: config.username,
+        uid: config.uid,
         addr: None,
         color: config.color,
+        presence: Presence::Online,
+        status: None,
     };
 
     match id_or_addr {
@@ -156,7 +252,7 @@ async fn join_room(id_or_addr: IdOrAddr, db: Arc<Mutex<DbRepo>>) -> Result<(), A
             match founded_room {
                 Either::Left(server_room) => {
                     let room_header = server_room.room_header();
-                    let mut server = ChatServer::new(server_room, db).await;
+                    let mut server = ChatServer::new(server_room, db.clone()).await;
                     server.run().await?;
                     sleep(Duration::from_millis(100)).await;
 
@@ -184,7 +280,9 @@ async fn join_room(id_or_addr: IdOrAddr, db: Arc<Mutex<DbRepo>>) -> Result<(), A
                     sleep(Duration::from_millis(200)).await;
                     server.set_owner_addr(client.user.lock().unwrap().addr.unwrap());
 
-                    ChatApp::new(client, config.light_mode).run().await?;
+                    ChatApp::new(client, config.light_mode, db, true)
+                        .run()
+                        .await?;
                     server.stop().await;
                 }
                 Either::Right(room_header) => {
@@ -214,7 +312,7 @@ async fn join_room(id_or_addr: IdOrAddr, db: Arc<Mutex<DbRepo>>) -> Result<(), A
                         .await
                         .unwrap();
 
-                    ChatApp::new(client, false).run().await?;
+                    ChatApp::new(client, false, db, false).run().await?;
                 }
             }
         }
@@ -271,7 +369,7 @@ async fn join_room(id_or_addr: IdOrAddr, db: Arc<Mutex<DbRepo>>) -> Result<(), A
                     .insert_one(client.room.lock().unwrap().clone())?;
             }
 
-            ChatApp::new(client, false).run().await?;
+            ChatApp::new(client, false, db, false).run().await?;
         }
     }
     Ok(())
@@ -280,6 +378,9 @@ async fn join_room(id_or_addr: IdOrAddr, db: Arc<Mutex<DbRepo>>) -> Result<(), A
 fn set_config(db: &DbRepo, option: &str, value: &str) -> Result<(), AppError> {
     match option {
         "username" => {
+            if !is_valid_username(value) {
+                return Err(AppError::InvalidArgument);
+            }
             db.local_data.update_one(
                 doc! {},
                 doc! {"$set": doc! {
@@ -309,6 +410,95 @@ fn set_config(db: &DbRepo, option: &str, value: &str) -> Result<(), AppError> {
                 }},
             )?;
         }
+        "away_after" | "max_file_size" => {
+            if let Ok(minutes) = u64::from_str(value) {
+                db.local_data.update_one(
+                    doc! {},
+                    doc! {"$set": doc! {
+                        option: minutes as i64
+                    }},
+                )?;
+            } else {
+                return Err(AppError::InvalidArgument);
+            }
+        }
+        "download_dir" => {
+            if !Path::new(value).is_dir() {
+                return Err(AppError::InvalidArgument);
+            }
+            db.local_data.update_one(
+                doc! {},
+                doc! {"$set": doc! {
+                    option: value
+                }},
+            )?;
+        }
+        "highlight_words" => {
+            let words = value
+                .split(',')
+                .map(|word| word.trim().to_string())
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<String>>();
+            db.local_data.update_one(
+                doc! {},
+                doc! {"$set": doc! {
+                    option: words
+                }},
+            )?;
+        }
+        "keymap" => {
+            if KeymapPreset::from_str(value).is_err() {
+                return Err(AppError::InvalidArgument);
+            }
+            db.local_data.update_one(
+                doc! {},
+                doc! {"$set": doc! {
+                    option: value
+                }},
+            )?;
+        }
+        "key_bindings" => {
+            let bindings = value
+                .split(',')
+                .map(|binding| binding.trim().to_string())
+                .filter(|binding| !binding.is_empty())
+                .collect::<Vec<String>>();
+            if bindings
+                .iter()
+                .any(|binding| keymap::parse_override(binding).is_err())
+            {
+                return Err(AppError::InvalidArgument);
+            }
+            db.local_data.update_one(
+                doc! {},
+                doc! {"$set": doc! {
+                    option: bindings
+                }},
+            )?;
+        }
+        "desktop_notify" => {
+            if DesktopNotify::from_str(value).is_err() {
+                return Err(AppError::InvalidArgument);
+            }
+            db.local_data.update_one(
+                doc! {},
+                doc! {"$set": doc! {
+                    option: value
+                }},
+            )?;
+        }
+        "read_receipts" | "bell" | "ignore_placeholder" | "keep_files" | "markdown" | "mouse" => {
+            if let Ok(state) = bool::from_str(value) {
+                db.local_data.update_one(
+                    doc! {},
+                    doc! {"$set": doc! {
+                        option: state
+                    }},
+                )?;
+            } else {
+                return Err(AppError::InvalidArgument);
+            }
+        }
         "light_mode" => {
             if let Ok(state) = bool::from_str(value) {
                 db.local_data.update_one(
@@ -355,6 +545,8 @@ pub enum CommandRequest {
     Join { id_or_address: IdOrAddr },
     Delete { room_id: String },
     Set { option: String, value: String },
+    Ignore { username: String },
+    Unignore { username: String },
     List,
     Invalid,
 }
@@ -401,6 +593,20 @@ pub fn get_command_request() -> CommandRequest {
                 value: value_str.to_string(),
             }
         }
+        Some(("ignore", ignore_matches)) => {
+            let username = ignore_matches
+                .get_one::<String>("username")
+                .unwrap()
+                .to_owned();
+            CommandRequest::Ignore { username }
+        }
+        Some(("unignore", unignore_matches)) => {
+            let username = unignore_matches
+                .get_one::<String>("username")
+                .unwrap()
+                .to_owned();
+            CommandRequest::Unignore { username }
+        }
         Some(("list", _)) => CommandRequest::List,
         _ => CommandRequest::Invalid,
     }
@@ -454,15 +660,34 @@ fn config_clap() -> ArgMatches {
                 .arg(Arg::new("option").required(true))
                 .arg(Arg::new("value").required(true)),
         )
+        .subcommand(
+            Command::new("ignore")
+                .long_flag("ignore")
+                .short_flag('i')
+                .about("Hides messages from a user by user id, or by username in rooms you host")
+                .arg(Arg::new("username").required(true)),
+        )
+        .subcommand(
+            Command::new("unignore")
+                .long_flag("unignore")
+                .short_flag('u')
+                .about("Shows messages from an ignored user again")
+                .arg(Arg::new("username").required(true)),
+        )
         .get_matches()
 }
 
 #[cfg(test)]
 mod test {
-    use super::{Color, CommandRequest, Config};
+    use super::{
+        Color, CommandRequest, Config, DesktopNotify, KeymapPreset, DEFAULT_MAX_FILE_SIZE,
+    };
     use crate::{
         app::{db_init, run_option},
-        schema::ServerRoom,
+        error::AppError,
+        network::{Presence, User},
+        schema::{ServerRoom, TextMessage},
+        util::{get_unique_id, is_valid_username},
     };
     use polodb_core::{bson::doc, CollectionT};
     use std::{
@@ -478,10 +703,24 @@ mod test {
         let db = db_init(&db_path).unwrap();
 
         let local_data = Config {
+            uid: "uid".into(),
             username: "*".into(),
             listener_addr: SocketAddr::from_str("127.0.0.1:12345").unwrap(),
             color: Color::White,
             light_mode: false,
+            away_after: 10,
+            read_receipts: false,
+            highlight_words: vec![],
+            bell: true,
+            desktop_notify: DesktopNotify::Off,
+            ignore_placeholder: true,
+            download_dir: None,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            keep_files: false,
+            markdown: true,
+            keymap: KeymapPreset::Default,
+            key_bindings: vec![],
+            mouse: false,
         };
 
         let local_data_from_db = db.local_data.find_one(doc! {}).unwrap().unwrap();
@@ -519,6 +758,19 @@ mod test {
             "someuser"
         );
 
+        assert!(is_valid_username(&get_unique_id()));
+        for invalid in ["", "some user", "some\tuser"] {
+            assert!(run_option(
+                CommandRequest::Set {
+                    option: "username".to_owned(),
+                    value: invalid.to_owned(),
+                },
+                db.clone(),
+            )
+            .await
+            .is_err());
+        }
+
         std::fs::remove_dir_all(&db_path).unwrap();
     }
 
@@ -532,6 +784,9 @@ mod test {
             addr: SocketAddr::from_str("127.0.0.1:12345".into()).unwrap(),
             passwd: None,
             banned_addrs: vec![],
+            topic: None,
+            motd: None,
+            pins: vec![],
         };
 
         run_option(
@@ -559,6 +814,9 @@ mod test {
             addr: SocketAddr::from_str("127.0.0.1:12345").unwrap(),
             passwd: None,
             banned_addrs: vec![],
+            topic: None,
+            motd: None,
+            pins: vec![],
         };
 
         run_option(
@@ -594,6 +852,9 @@ mod test {
             addr: SocketAddr::from_str("127.0.0.1:12345").unwrap(),
             passwd: None,
             banned_addrs: vec![],
+            topic: None,
+            motd: None,
+            pins: vec![],
         };
 
         run_option(
@@ -636,4 +897,96 @@ mod test {
 
         std::fs::remove_dir_all(&db_path).unwrap();
     }
+
+    #[tokio::test]
+    async fn user_ignoring() {
+        let db_path = Path::new("db");
+        let db = Arc::new(Mutex::new(db_init(db_path).unwrap()));
+        let user = User {
+            id: "someuser".into(),
+            uid: "someuid".into(),
+            addr: Some(SocketAddr::from_str("127.0.0.1:12345").unwrap()),
+            color: Color::White,
+            presence: Presence::Online,
+            status: None,
+        };
+        db.lock()
+            .unwrap()
+            .messages
+            .insert_one(TextMessage::new(&user, "someroom", "hello"))
+            .unwrap();
+
+        run_option(
+            CommandRequest::Ignore {
+                username: "someuser".into(),
+            },
+            db.clone(),
+        )
+        .await
+        .unwrap();
+
+        let ignored_user = db
+            .lock()
+            .unwrap()
+            .ignored_users
+            .find_one(doc! {"_id": "someuid"})
+            .unwrap()
+            .unwrap();
+        assert_eq!(ignored_user.username, "someuser");
+
+        assert!(matches!(
+            run_option(
+                CommandRequest::Ignore {
+                    username: "unknownuser".into(),
+                },
+                db.clone(),
+            )
+            .await,
+            Err(AppError::UnknownUser)
+        ));
+
+        run_option(
+            CommandRequest::Unignore {
+                username: "someuser".into(),
+            },
+            db.clone(),
+        )
+        .await
+        .unwrap();
+
+        assert!(db
+            .lock()
+            .unwrap()
+            .ignored_users
+            .find_one(doc! {"_id": "someuid"})
+            .unwrap()
+            .is_none());
+
+        let uid = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        run_option(
+            CommandRequest::Ignore {
+                username: uid.into(),
+            },
+            db.clone(),
+        )
+        .await
+        .unwrap();
+        run_option(
+            CommandRequest::Unignore {
+                username: uid.into(),
+            },
+            db.clone(),
+        )
+        .await
+        .unwrap();
+        assert!(db
+            .lock()
+            .unwrap()
+            .ignored_users
+            .find_one(doc! {"_id": uid})
+            .unwrap()
+            .is_none());
+
+        std::fs::remove_dir_all(db_path).unwrap();
+    }
 }
@@ -1,4 +1,6 @@
-use crate::schema::{Config, RoomHeader, ServerRoom, TextMessage};
+use crate::schema::{
+    Config, IgnoredUser, InputHistory, ReadMarker, RoomHeader, ServerRoom, TextMessage,
+};
 use polodb_core::{Collection, Database, Result as pdbResult};
 use std::path::Path;
 
@@ -7,6 +9,9 @@ pub struct DbRepo {
     pub room_headers: Collection<RoomHeader>,
     pub messages: Collection<TextMessage>,
     pub local_data: Collection<Config>,
+    pub read_markers: Collection<ReadMarker>,
+    pub ignored_users: Collection<IgnoredUser>,
+    pub input_history: Collection<InputHistory>,
     _db: Database,
 }
 
@@ -19,6 +24,9 @@ impl DbRepo {
             messages: db.collection::<TextMessage>("messages"),
             room_headers: db.collection::<RoomHeader>("room_headers"),
             server_rooms: db.collection::<ServerRoom>("server_rooms"),
+            read_markers: db.collection::<ReadMarker>("read_markers"),
+            ignored_users: db.collection::<IgnoredUser>("ignored_users"),
+            input_history: db.collection::<InputHistory>("input_history"),
             _db: db,
         })
     }
@@ -12,6 +12,8 @@ pub enum AppError {
     AlreadyExistingId,
     #[error("Data not found in database.")]
     DataNotFound,
+    #[error("Unknown user. Usernames are only known from rooms you host, pass the user id shown by /ignore instead.")]
+    UnknownUser,
     #[error("Authorization failure.")]
     AuthFailure,
     #[error("Connection refused")]
@@ -18,6 +18,7 @@ async fn main() {
             AppError::IoError(err) => error!("{}", err),
             AppError::AlreadyExistingId => println!("{}", err),
             AppError::DataNotFound => println!("{}", err),
+            AppError::UnknownUser => println!("{}", err),
             AppError::AuthFailure => println!("{}", err),
             AppError::ConnectionRefused => println!("{}", err),
             AppError::NotExistingId => println!("{}", err),
@@ -62,11 +62,25 @@ impl ChatClient {
                         let deserialize_result = Message::try_from(msg);
                         if let Ok(deserialized_msg) = &deserialize_result {
                             if let MessageType::Server(ServerMsg::Sync {
-                                room_id, user_addr, ..
+                                room_id,
+                                user_addr,
+                                users,
+                                ..
                             }) = &deserialized_msg.msg_type
                             {
                                 shared_room.lock().unwrap()._id = room_id.clone();
-                                shared_user.lock().unwrap().addr = Some(*user_addr);
+                                let mut shared_user = shared_user.lock().unwrap();
+                                shared_user.addr = Some(*user_addr);
+                                if let Some(user) =
+                                    users.iter().find(|user| user.addr == Some(*user_addr))
+                                {
+                                    shared_user.uid = user.uid.clone();
+                                }
+                            }
+                            if let MessageType::Server(ServerMsg::NickAssigned { id }) =
+                                &deserialized_msg.msg_type
+                            {
+                                shared_user.lock().unwrap().id = id.clone();
                             }
 
                             if let Err(err) = tx_in.send(deserialized_msg.clone()).await {
@@ -1,5 +1,5 @@
-use super::User;
-use crate::schema::TextMessage;
+use super::{transfer::FileInfo, Presence, User};
+use crate::schema::{Reaction, TextMessage};
 use serde::{Deserialize, Serialize};
 use serde_json::{from_str, to_string};
 use std::net::SocketAddr;
@@ -45,10 +45,69 @@ pub enum MessageType {
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub enum UserMsg {
-    Normal { msg: TextMessage },
-    UserJoined { user: User },
-    SyncReq { user: User },
-    BanReq { addr: SocketAddr },
+    Normal {
+        msg: TextMessage,
+    },
+    Private {
+        msg: TextMessage,
+        recipient: SocketAddr,
+    },
+    UserJoined {
+        user: User,
+    },
+    UserUpdate {
+        user: User,
+    },
+    Edit {
+        msg_id: String,
+        content: String,
+    },
+    Delete {
+        msg_id: String,
+    },
+    React {
+        msg_id: String,
+        emoji: String,
+    },
+    Typing {
+        is_typing: bool,
+    },
+    Presence {
+        presence: Presence,
+        status: Option<String>,
+    },
+    Read {
+        msg_id: String,
+    },
+    Topic {
+        topic: Option<String>,
+    },
+    Motd {
+        motd: Option<String>,
+    },
+    Pin {
+        msg_id: String,
+    },
+    FileOffer {
+        file: FileInfo,
+    },
+    FileChunk {
+        file_id: String,
+        index: u64,
+        data: String,
+    },
+    FileReq {
+        file_id: String,
+    },
+    SyncReq {
+        user: User,
+    },
+    BanReq {
+        addr: SocketAddr,
+    },
+    SearchReq {
+        pattern: String,
+    },
     Auth,
 }
 
@@ -63,6 +122,10 @@ pub enum ServerMsg {
         room_id: String,
         messages: Vec<TextMessage>,
         users: Vec<User>,
+        topic: Option<String>,
+        motd: Option<String>,
+        pins: Vec<String>,
+        files: Vec<FileInfo>,
     },
     UserLeft {
         addr: SocketAddr,
@@ -70,6 +133,68 @@ pub enum ServerMsg {
     BanConfirm {
         addr: SocketAddr,
     },
+    NickTaken {
+        id: String,
+    },
+    NickAssigned {
+        id: String,
+    },
+    MsgEdited {
+        msg_id: String,
+        content: String,
+    },
+    MsgDeleted {
+        msg_id: String,
+    },
+    Reactions {
+        msg_id: String,
+        reactions: Vec<Reaction>,
+    },
+    Typing {
+        addr: SocketAddr,
+        is_typing: bool,
+    },
+    ReadReceipt {
+        addr: SocketAddr,
+        msg_id: String,
+    },
+    PresenceChanged {
+        addr: SocketAddr,
+        presence: Presence,
+        status: Option<String>,
+    },
+    TopicChanged {
+        addr: SocketAddr,
+        topic: Option<String>,
+    },
+    MotdChanged {
+        addr: SocketAddr,
+        motd: Option<String>,
+    },
+    Pinned {
+        addr: SocketAddr,
+        msg_id: String,
+        pinned: bool,
+    },
+    FileOffer {
+        file: FileInfo,
+    },
+    FileChunk {
+        file_id: String,
+        index: u64,
+        data: String,
+    },
+    FileRejected {
+        file_id: String,
+        reason: String,
+    },
+    SearchResults {
+        pattern: String,
+        messages: Vec<TextMessage>,
+    },
+    Rejected {
+        reason: String,
+    },
     ServerShutdown,
 }
 
@@ -1,6 +1,7 @@
 pub mod client;
 pub mod message;
 pub mod server;
+pub mod transfer;
 
 use crate::schema::Color;
 use serde::{Deserialize, Serialize};
@@ -9,8 +10,31 @@ use std::net::SocketAddr;
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct User {
     pub id: String,
+    #[serde(default)]
+    pub uid: String,
     pub addr: Option<SocketAddr>,
     pub color: Color,
+    #[serde(default)]
+    pub presence: Presence,
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum Presence {
+    #[default]
+    Online,
+    Away,
+    DoNotDisturb,
+}
+
+impl Presence {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Presence::Online => "●",
+            Presence::Away => "◐",
+            Presence::DoNotDisturb => "⊘",
+        }
+    }
 }
 
 #[cfg(test)]
@@ -21,20 +45,24 @@ mod test {
             client::ChatClient,
             message::MessageType,
             message::{ServerMsg, UserMsg},
-            server::ChatServer,
-            User,
+            server::{ChatServer, PeerMap},
+            transfer::{
+                FileBuffer, FileInfo, FileStore, IncomingFile, OutgoingFile, Upload, CHUNK_SIZE,
+            },
+            Presence, User,
         },
         schema::{Color, ServerRoom, TextMessage},
         util::hash_passwd,
     };
     use bson::doc;
+    use futures_channel::mpsc::unbounded;
     use polodb_core::CollectionT;
     use std::{
         net::SocketAddr,
         path::Path,
         str::FromStr,
         sync::{Arc, Mutex},
-        time::Duration,
+        time::{Duration, SystemTime},
     };
     use tokio::time::sleep;
 
@@ -47,17 +75,26 @@ mod test {
             addr: SocketAddr::from_str("127.0.0.1:12345").unwrap(),
             passwd: Some(passwd),
             banned_addrs: vec![],
+            topic: None,
+            motd: None,
+            pins: vec![],
         };
         let header = room.room_header();
         let user = User {
             id: "user1".into(),
+            uid: "uid1".into(),
             addr: None,
             color: Color::LightRed,
+            presence: Presence::Online,
+            status: None,
         };
         let user2 = User {
             id: "user2".into(),
+            uid: "uid2".into(),
             addr: None,
             color: Color::LightGreen,
+            presence: Presence::Online,
+            status: None,
         };
 
         let db_path = Path::new("db");
@@ -163,8 +200,25 @@ mod test {
             assert!(false);
         }
 
-        let mut sended_msg2 = sended_msg.clone();
-        sended_msg2.sender_addr = client2.user.lock().unwrap().addr.unwrap();
+        client2
+            .send_msg(UserMsg::Normal {
+                msg: sended_msg.clone(),
+            })
+            .await
+            .unwrap();
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(
+            client2.recv_msg().await.unwrap(),
+            MessageType::Server(ServerMsg::Rejected {
+                reason: "Message id already in use".into()
+            })
+        );
+
+        let sended_msg2 = TextMessage::new(
+            &client2.user.lock().unwrap(),
+            &client2.room.lock().unwrap()._id,
+            "some short message",
+        );
         client2
             .send_msg(UserMsg::Normal {
                 msg: sended_msg2.clone(),
@@ -179,6 +233,70 @@ mod test {
             assert_eq!(msg.content, sended_msg2.content);
         }
 
+        client2
+            .send_msg(UserMsg::Topic {
+                topic: Some("some topic".into()),
+            })
+            .await
+            .unwrap();
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(
+            client2.recv_msg().await.unwrap(),
+            MessageType::Server(ServerMsg::Rejected {
+                reason: "Only the room owner can change the topic".into()
+            })
+        );
+
+        client2
+            .send_msg(UserMsg::Pin {
+                msg_id: sended_msg2.msg_id.clone(),
+            })
+            .await
+            .unwrap();
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(
+            client2.recv_msg().await.unwrap(),
+            MessageType::Server(ServerMsg::Rejected {
+                reason: "Only the room owner can pin messages".into()
+            })
+        );
+
+        client2
+            .send_msg(UserMsg::Delete {
+                msg_id: sended_msg.msg_id.clone(),
+            })
+            .await
+            .unwrap();
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(
+            client2.recv_msg().await.unwrap(),
+            MessageType::Server(ServerMsg::Rejected {
+                reason: "You can only change your own messages from the last 15 minutes".into()
+            })
+        );
+
+        let whisper = TextMessage::new(
+            &client2.user.lock().unwrap(),
+            &client2.room.lock().unwrap()._id,
+            "a whisper",
+        );
+        let whisper_recipient = client.user.lock().unwrap().addr.unwrap();
+        client2
+            .send_msg(UserMsg::Private {
+                msg: whisper.clone(),
+                recipient: whisper_recipient,
+            })
+            .await
+            .unwrap();
+        sleep(Duration::from_millis(100)).await;
+        for receiver in [&mut client, &mut client2] {
+            assert!(matches!(
+                receiver.recv_msg().await.unwrap(),
+                MessageType::User(UserMsg::Private { msg, recipient })
+                    if msg.msg_id == whisper.msg_id && recipient == whisper_recipient
+            ));
+        }
+
         client
             .send_msg(UserMsg::BanReq {
                 addr: client2.user.lock().unwrap().addr.unwrap(),
@@ -205,6 +323,22 @@ mod test {
             })
         );
 
+        let left_addr = client2.user.lock().unwrap().addr.unwrap();
+        client
+            .send_msg(UserMsg::Private {
+                msg: sended_msg.clone(),
+                recipient: left_addr,
+            })
+            .await
+            .unwrap();
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(
+            client.recv_msg().await.unwrap(),
+            MessageType::Server(ServerMsg::Rejected {
+                reason: "User not found".into()
+            })
+        );
+
         server.stop().await;
         assert_eq!(
             client.recv_msg().await.unwrap(),
@@ -227,4 +361,276 @@ mod test {
 
         std::fs::remove_dir_all(&db_path).unwrap();
     }
+
+    #[test]
+    fn nick_collisions() {
+        let peer_map = PeerMap::default();
+        let addrs = ["127.0.0.1:23457", "127.0.0.1:23458", "127.0.0.1:23459"]
+            .map(|addr| SocketAddr::from_str(addr).unwrap());
+        let register = |addr: SocketAddr, id: &str| {
+            let user = User {
+                id: id.into(),
+                uid: id.into(),
+                addr: Some(addr),
+                color: Color::White,
+                presence: Presence::Online,
+                status: None,
+            };
+            peer_map
+                .lock()
+                .unwrap()
+                .insert(addr, (unbounded().0, Some(user)));
+        };
+
+        assert_eq!(
+            ChatServer::unique_nick(&peer_map, "alice", addrs[0]),
+            "alice"
+        );
+        register(addrs[0], "alice");
+        assert_eq!(
+            ChatServer::unique_nick(&peer_map, "alice", addrs[0]),
+            "alice"
+        );
+        assert!(ChatServer::is_nick_taken(&peer_map, "alice", addrs[1]));
+        assert!(ChatServer::is_nick_taken(&peer_map, "Alice", addrs[1]));
+        assert!(!ChatServer::is_nick_taken(&peer_map, "bob", addrs[1]));
+
+        assert_eq!(
+            ChatServer::unique_nick(&peer_map, "Alice", addrs[1]),
+            "Alice_2"
+        );
+        register(addrs[1], "Alice_2");
+        assert_eq!(
+            ChatServer::unique_nick(&peer_map, "ALICE", addrs[2]),
+            "ALICE_3"
+        );
+    }
+
+    #[test]
+    fn editable_msgs() {
+        let db_path = Path::new("db_editable_msgs");
+        let db = Arc::new(Mutex::new(DbRepo::new(db_path).unwrap()));
+        let room = Arc::new(Mutex::new(ServerRoom {
+            _id: "firstroom".into(),
+            addr: SocketAddr::from_str("127.0.0.1:12345").unwrap(),
+            passwd: None,
+            banned_addrs: vec![],
+            topic: None,
+            motd: None,
+            pins: vec![],
+        }));
+        let sender_addr = SocketAddr::from_str("127.0.0.1:23460").unwrap();
+        let user = User {
+            id: "user1".into(),
+            uid: "uid4".into(),
+            addr: Some(sender_addr),
+            color: Color::White,
+            presence: Presence::Online,
+            status: None,
+        };
+
+        let recent = TextMessage::new(&user, "firstroom", "recent");
+        let mut stale = TextMessage::new(&user, "firstroom", "stale");
+        stale.timestamp = Some(SystemTime::now() - Duration::from_secs(16 * 60));
+        let mut deleted = TextMessage::new(&user, "firstroom", "deleted");
+        deleted.deleted = true;
+        let elsewhere = TextMessage::new(&user, "secondroom", "elsewhere");
+        db.lock()
+            .unwrap()
+            .messages
+            .insert_many([&recent, &stale, &deleted, &elsewhere])
+            .unwrap();
+
+        let editable = |msg: &TextMessage, uid: &str, is_moderator: bool| {
+            ChatServer::find_editable_msg(&msg.msg_id, uid, &room, &db, is_moderator).is_ok()
+        };
+        assert!(editable(&recent, "uid4", false));
+        assert!(!editable(&recent, "uid6", false));
+        assert!(!editable(&recent, "", false));
+        assert!(editable(&recent, "uid6", true));
+        assert!(!editable(&stale, "uid4", false));
+        assert!(editable(&stale, "uid6", true));
+        assert!(!editable(&deleted, "uid4", false));
+        assert!(!editable(&deleted, "uid6", true));
+        assert!(!editable(&elsewhere, "uid4", false));
+        assert!(!editable(&elsewhere, "uid6", true));
+        assert_eq!(
+            ChatServer::find_editable_msg(&recent.msg_id, "uid6", &room, &db, false),
+            Err("You can only change your own messages from the last 15 minutes".into())
+        );
+
+        std::fs::remove_dir_all(db_path).unwrap();
+    }
+
+    #[test]
+    fn sender_history_update() {
+        let db_path = Path::new("db_sender_history");
+        let db = Arc::new(Mutex::new(DbRepo::new(db_path).unwrap()));
+        let addr = SocketAddr::from_str("127.0.0.1:23456").unwrap();
+        let user = User {
+            id: "user1".into(),
+            uid: "uid5".into(),
+            addr: Some(addr),
+            color: Color::LightRed,
+            presence: Presence::Online,
+            status: None,
+        };
+        let first_msg = TextMessage::new(&user, "firstroom", "hello");
+        let second_msg = TextMessage::new(&user, "secondroom", "hello");
+        let reconnected = User {
+            addr: Some(SocketAddr::from_str("127.0.0.1:23457").unwrap()),
+            ..user.clone()
+        };
+        let reconnected_msg = TextMessage::new(&reconnected, "firstroom", "hello again");
+        let other = User {
+            id: "user3".into(),
+            uid: "uid7".into(),
+            ..user.clone()
+        };
+        let other_msg = TextMessage::new(&other, "firstroom", "hello");
+        db.lock()
+            .unwrap()
+            .messages
+            .insert_many([&first_msg, &second_msg, &reconnected_msg, &other_msg])
+            .unwrap();
+
+        let renamed = User {
+            id: "user2".into(),
+            color: Color::LightBlue,
+            ..user
+        };
+        ChatServer::update_sender_history(&db, "firstroom", &renamed);
+
+        let find_msg = |msg_id: &str| {
+            db.lock()
+                .unwrap()
+                .messages
+                .find_one(doc! {"msg_id": msg_id})
+                .unwrap()
+                .unwrap()
+        };
+        for msg_id in [&first_msg.msg_id, &reconnected_msg.msg_id] {
+            let msg = find_msg(msg_id);
+            assert_eq!(msg.last_username, "user2");
+            assert_eq!(msg.last_color, Color::LightBlue);
+        }
+        let second_msg = find_msg(&second_msg.msg_id);
+        assert_eq!(second_msg.last_username, "user1");
+        assert_eq!(second_msg.last_color, Color::LightRed);
+        let other_msg = find_msg(&other_msg.msg_id);
+        assert_eq!(other_msg.last_username, "user3");
+
+        std::fs::remove_dir_all(db_path).unwrap();
+    }
+
+    #[test]
+    fn file_chunking() {
+        let data = (0..CHUNK_SIZE * 2 + 10)
+            .map(|i| i as u8)
+            .collect::<Vec<u8>>();
+        let info = FileInfo::new(
+            Path::new("some/file.bin"),
+            &data,
+            SocketAddr::from_str("127.0.0.1:12345").unwrap(),
+            "user1",
+        );
+        assert_eq!(info.name, "file.bin");
+        assert_eq!(info.chunk_count(), 3);
+
+        let mut outgoing = OutgoingFile::new(info.clone(), data.clone());
+        let mut incoming = FileBuffer::new(info.clone());
+        while let Some((index, chunk)) = outgoing.next_chunk() {
+            incoming.push_chunk(index, &chunk).unwrap();
+        }
+        assert!(outgoing.is_done());
+        assert!(incoming.is_complete());
+        assert!(incoming.is_valid());
+        assert_eq!(incoming.data, data);
+
+        let mut tampered = FileBuffer::new(FileInfo {
+            checksum: "0".repeat(64),
+            ..info
+        });
+        for (index, chunk) in incoming.chunks() {
+            tampered.push_chunk(index, &chunk).unwrap();
+        }
+        assert!(tampered.is_complete());
+        assert!(!tampered.is_valid());
+        assert!(tampered.push_chunk(0, "AAAA").is_err());
+    }
+
+    #[test]
+    fn file_streaming() {
+        let dir = Path::new("downloads_streaming");
+        let _ = std::fs::remove_dir_all(dir);
+        let data = (0..CHUNK_SIZE + 10).map(|i| i as u8).collect::<Vec<u8>>();
+        let info = FileInfo::new(
+            Path::new("file.bin"),
+            &data,
+            SocketAddr::from_str("127.0.0.1:12345").unwrap(),
+            "user1",
+        );
+
+        let mut outgoing = OutgoingFile::new(info.clone(), data.clone());
+        let mut incoming = IncomingFile::create(info.clone(), dir).unwrap();
+        assert!(incoming.push_chunk(1, "AAAA").is_err());
+        while let Some((index, chunk)) = outgoing.next_chunk() {
+            incoming.push_chunk(index, &chunk).unwrap();
+        }
+        assert!(incoming.is_complete());
+        assert_eq!(incoming.progress(), 100);
+        let path = incoming.finish().unwrap();
+        assert_eq!(path, dir.join("file.bin"));
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+
+        let mut tampered = IncomingFile::create(
+            FileInfo {
+                checksum: "0".repeat(64),
+                ..info
+            },
+            dir,
+        )
+        .unwrap();
+        let tampered_path = tampered.path.clone();
+        assert_eq!(tampered_path, dir.join("file_2.bin"));
+        for (index, chunk) in OutgoingFile::new(tampered.info.clone(), data)
+            .buffer
+            .chunks()
+        {
+            tampered.push_chunk(index, &chunk).unwrap();
+        }
+        assert!(tampered.finish().is_err());
+        assert!(!tampered_path.exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn file_store_uploads() {
+        let addr1 = SocketAddr::from_str("127.0.0.1:12345").unwrap();
+        let addr2 = SocketAddr::from_str("127.0.0.1:12346").unwrap();
+        let info = FileInfo::new(Path::new("file.bin"), b"data", addr1, "user1");
+
+        let mut store = FileStore::new(1024, false);
+        store.uploads.insert(
+            info.file_id.clone(),
+            Upload {
+                buffer: FileBuffer::new(info.clone()),
+                receivers: vec![],
+            },
+        );
+        assert!(store.contains(&info.file_id));
+        assert!(!store.contains("other"));
+        assert_eq!(store.uploads_from(addr1), 1);
+        assert_eq!(store.uploads_from(addr2), 0);
+
+        let upload = store.uploads.remove(&info.file_id).unwrap();
+        store.store(upload.buffer.clone());
+        assert!(!store.contains(&info.file_id));
+        assert!(store.shared().is_empty());
+        store.keep_files = true;
+        store.store(upload.buffer);
+        assert!(store.contains(&info.file_id));
+        assert_eq!(store.shared(), vec![info]);
+    }
 }
@@ -1,16 +1,20 @@
 use super::{
     message::{Message, MessageType, ServerMsg, UserMsg},
+    transfer::{FileBuffer, FileStore, Upload, MAX_UPLOADS_PER_PEER},
     User,
 };
 use crate::{
     db::DbRepo,
-    schema::{ServerRoom, TextMessage},
+    schema::{ServerRoom, TextMessage, DEFAULT_MAX_FILE_SIZE},
+    tui::emoji,
+    util::{get_unique_id, is_valid_username},
 };
 use bson::doc;
 use futures_channel::mpsc::{unbounded, UnboundedSender};
 use futures_util::{future, pin_mut, StreamExt, TryStreamExt};
 use log::{error, warn};
 use polodb_core::CollectionT;
+use regex::Regex;
 use std::{
     collections::HashMap,
     io,
@@ -27,24 +31,46 @@ use tokio_tungstenite::{
     tungstenite::{Error as TtError, Message as TtMessage},
 };
 use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
 
 type Tx = UnboundedSender<TtMessage>;
-type PeerMap = Arc<Mutex<HashMap<SocketAddr, (Tx, Option<User>)>>>;
+pub(super) type PeerMap = Arc<Mutex<HashMap<SocketAddr, (Tx, Option<User>)>>>;
 type Unauthorized = bool;
 
+const EDIT_WINDOW: Duration = Duration::from_secs(15 * 60);
+const MAX_SEARCH_RESULTS: usize = 20;
+
 pub struct ChatServer {
     pub(super) room: Arc<Mutex<ServerRoom>>,
     pub owner_addr: Arc<Mutex<Option<SocketAddr>>>,
     peer_map: PeerMap,
+    files: Arc<Mutex<FileStore>>,
     db: Arc<Mutex<DbRepo>>,
     finisher: CancellationToken,
 }
 
 impl ChatServer {
     pub async fn new(room: ServerRoom, db: Arc<Mutex<DbRepo>>) -> ChatServer {
+        let config = match db.lock().unwrap().local_data.find_one(doc! {}) {
+            Ok(config) => config,
+            Err(err) => {
+                warn!("{}", err);
+                None
+            }
+        };
+        let files = FileStore::new(
+            config
+                .as_ref()
+                .map_or(DEFAULT_MAX_FILE_SIZE, |config| config.max_file_size)
+                * 1024
+                * 1024,
+            config.is_some_and(|config| config.keep_files),
+        );
+
         Self {
             peer_map: PeerMap::new(Mutex::new(HashMap::new())),
             owner_addr: Arc::new(Mutex::new(None)),
+            files: Arc::new(Mutex::new(files)),
             room: Arc::new(Mutex::new(room)),
             db,
             finisher: CancellationToken::new(),
@@ -58,6 +84,7 @@ impl ChatServer {
         let addr = self.room.lock().unwrap().addr;
         let cloned_token = self.finisher.clone();
         let owner_addr = self.owner_addr.clone();
+        let files = self.files.clone();
 
         tokio::spawn(async move {
             let cloned_token = cloned_token.clone();
@@ -87,6 +114,7 @@ impl ChatServer {
                                             db.clone(),
                                             cloned_token_.clone(),
                                             owner_addr.clone(),
+                                            files.clone(),
                                         ));
                                     }
                                 }
@@ -126,6 +154,7 @@ impl ChatServer {
         self.finisher.cancel();
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn handle_conection(
         peer_map: PeerMap,
         stream: TcpStream,
@@ -134,6 +163,7 @@ impl ChatServer {
         db: Arc<Mutex<DbRepo>>,
         finisher: CancellationToken,
         owner_addr: Arc<Mutex<Option<SocketAddr>>>,
+        files: Arc<Mutex<FileStore>>,
     ) -> Result<(), TtError> {
         let ws_stream = accept_async(stream).await?;
 
@@ -165,6 +195,7 @@ impl ChatServer {
                 room.clone(),
                 db.clone(),
                 owner_addr.clone(),
+                files.clone(),
             );
 
             let is_banned = room.lock().unwrap().banned_addrs.iter().any(|&a| a == addr);
@@ -187,6 +218,30 @@ impl ChatServer {
 
         peer_map.lock().unwrap().remove(&addr);
 
+        let abandoned_uploads = {
+            let mut files = files.lock().unwrap();
+            let file_ids = files
+                .uploads
+                .iter()
+                .filter(|(_, upload)| upload.buffer.info.sender_addr == addr)
+                .map(|(file_id, _)| file_id.clone())
+                .collect::<Vec<String>>();
+            files
+                .uploads
+                .retain(|_, upload| upload.buffer.info.sender_addr != addr);
+            file_ids
+        };
+        for file_id in abandoned_uploads {
+            Self::send_to_all(
+                Message::from(ServerMsg::FileRejected {
+                    file_id,
+                    reason: "Sender has left".to_string(),
+                }),
+                peer_map.clone(),
+                None,
+            );
+        }
+
         if !*unauthorized.lock().unwrap() && *first_joined.lock().unwrap() {
             Self::send_to_all(
                 Message {
@@ -232,6 +287,128 @@ impl ChatServer {
         }
     }
 
+    pub(super) fn is_nick_taken(peer_map: &PeerMap, id: &str, addr: SocketAddr) -> bool {
+        let id = id.to_lowercase();
+        peer_map
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(&peer_addr, (_, peer))| {
+                peer_addr != addr
+                    && peer
+                        .as_ref()
+                        .is_some_and(|peer| peer.id.to_lowercase() == id)
+            })
+    }
+
+    fn is_uid_taken(peer_map: &PeerMap, uid: &str, addr: SocketAddr) -> bool {
+        peer_map
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(&peer_addr, (_, peer))| {
+                peer_addr != addr && peer.as_ref().is_some_and(|peer| peer.uid == uid)
+            })
+    }
+
+    fn registered_user(peer_map: &PeerMap, addr: SocketAddr) -> Option<User> {
+        peer_map
+            .lock()
+            .unwrap()
+            .get(&addr)
+            .and_then(|(_, user)| user.clone())
+    }
+
+    pub(super) fn unique_nick(peer_map: &PeerMap, id: &str, addr: SocketAddr) -> String {
+        if !Self::is_nick_taken(peer_map, id, addr) {
+            return id.to_string();
+        }
+        (2..)
+            .map(|n| format!("{}_{}", id, n))
+            .find(|candidate| !Self::is_nick_taken(peer_map, candidate, addr))
+            .unwrap()
+    }
+
+    pub(super) fn find_editable_msg(
+        msg_id: &str,
+        user_uid: &str,
+        room: &Arc<Mutex<ServerRoom>>,
+        db: &Arc<Mutex<DbRepo>>,
+        is_moderator: bool,
+    ) -> Result<TextMessage, String> {
+        let not_found = || "Message not found".to_string();
+        let room_id = room.lock().unwrap()._id.clone();
+        let text_msg = match db
+            .lock()
+            .unwrap()
+            .messages
+            .find_one(doc! {"msg_id": msg_id, "room_id": room_id})
+        {
+            Ok(text_msg) => text_msg.ok_or_else(not_found)?,
+            Err(err) => {
+                warn!("{}", err);
+                return Err(not_found());
+            }
+        };
+
+        if text_msg.deleted {
+            return Err(not_found());
+        }
+        if is_moderator {
+            return Ok(text_msg);
+        }
+
+        let is_recent = text_msg
+            .timestamp
+            .and_then(|ts| ts.elapsed().ok())
+            .is_some_and(|elapsed| elapsed <= EDIT_WINDOW);
+        if !user_uid.is_empty() && text_msg.sender_uid == user_uid && is_recent {
+            Ok(text_msg)
+        } else {
+            warn!("{} is not allowed to modify message {}", user_uid, msg_id);
+            Err("You can only change your own messages from the last 15 minutes".to_string())
+        }
+    }
+
+    pub(super) fn update_sender_history(db: &Arc<Mutex<DbRepo>>, room_id: &str, user: &User) {
+        if user.uid.is_empty() {
+            return;
+        }
+        let update_result = db.lock().unwrap().messages.update_many(
+            doc! {"room_id": room_id, "sender_uid": user.uid.clone()},
+            doc! {"$set": doc! {
+                "last_username": user.id.clone(),
+                "last_color": user.color.to_string(),
+            }},
+        );
+        if let Err(err) = update_result {
+            warn!("{}", err);
+        }
+    }
+
+    fn save_room_info(room: &Arc<Mutex<ServerRoom>>, db: &Arc<Mutex<DbRepo>>) {
+        let (room_id, topic, motd, pins) = {
+            let room = room.lock().unwrap();
+            (
+                room._id.clone(),
+                room.topic.clone(),
+                room.motd.clone(),
+                room.pins.clone(),
+            )
+        };
+        let result = db.lock().unwrap().server_rooms.update_one(
+            doc! {"_id": room_id},
+            doc! {"$set": doc! {
+                "topic": topic,
+                "motd": motd,
+                "pins": pins,
+            }},
+        );
+        if let Err(err) = result {
+            warn!("{}", err);
+        }
+    }
+
     fn handle_message(
         msg: Message,
         peer_map: PeerMap,
@@ -239,6 +416,7 @@ impl ChatServer {
         room: Arc<Mutex<ServerRoom>>,
         db: Arc<Mutex<DbRepo>>,
         owner_addr: Arc<Mutex<Option<SocketAddr>>>,
+        files: Arc<Mutex<FileStore>>,
     ) -> Unauthorized {
         if let Some(passwd) = &room.lock().unwrap().passwd {
             if let Some(msg_passwd) = &msg.passwd {
@@ -269,7 +447,54 @@ impl ChatServer {
         if let MessageType::User(user_msg) = msg.msg_type {
             match user_msg {
                 UserMsg::Normal { msg: mut text_msg } => {
+                    let sender = peer_map
+                        .lock()
+                        .unwrap()
+                        .get(&addr)
+                        .and_then(|(_, user)| user.clone());
+                    let Some(sender) = sender else {
+                        warn!("Message from unregistered peer {}", addr);
+                        return false;
+                    };
+                    let is_id_free = !text_msg.msg_id.is_empty()
+                        && matches!(
+                            db.lock()
+                                .unwrap()
+                                .messages
+                                .find_one(doc! {"msg_id": &text_msg.msg_id}),
+                            Ok(None)
+                        );
+                    if !is_id_free {
+                        warn!("Duplicate message id {} from {}", text_msg.msg_id, addr);
+                        Self::send_to_one(
+                            Message::from(ServerMsg::Rejected {
+                                reason: "Message id already in use".to_string(),
+                            }),
+                            peer_map.clone(),
+                            &addr,
+                        );
+                        return false;
+                    }
+
+                    let room_id = room.lock().unwrap()._id.clone();
+                    text_msg.room_id = room_id.clone();
+                    text_msg.sender_addr = addr;
+                    text_msg.sender_uid = sender.uid;
+                    text_msg.last_username = sender.id;
+                    text_msg.last_color = sender.color;
                     text_msg.timestamp = Some(SystemTime::now());
+                    if let Some(parent_id) = &text_msg.in_reply_to {
+                        let parent_exists = db
+                            .lock()
+                            .unwrap()
+                            .messages
+                            .find_one(doc! {"msg_id": parent_id, "room_id": room_id})
+                            .is_ok_and(|parent| parent.is_some());
+                        if !parent_exists {
+                            warn!("Replied message {} not found", parent_id);
+                            text_msg.in_reply_to = None;
+                        }
+                    }
                     Self::send_to_all(
                         Message::from((
                             UserMsg::Normal {
@@ -284,8 +509,51 @@ impl ChatServer {
                         warn!("{}", err);
                     }
                 }
+                UserMsg::Private {
+                    msg: mut text_msg,
+                    recipient,
+                } => {
+                    let sender = peer_map
+                        .lock()
+                        .unwrap()
+                        .get(&addr)
+                        .and_then(|(_, user)| user.clone());
+                    let Some(sender) = sender else {
+                        warn!("Private message from unregistered peer {}", addr);
+                        return false;
+                    };
+                    text_msg.timestamp = Some(SystemTime::now());
+                    text_msg.sender_addr = addr;
+                    text_msg.sender_uid = sender.uid;
+                    text_msg.last_username = sender.id;
+                    text_msg.last_color = sender.color;
+                    if peer_map.lock().unwrap().contains_key(&recipient) {
+                        let msg = Message::from((
+                            UserMsg::Private {
+                                msg: text_msg,
+                                recipient,
+                            },
+                            None,
+                        ));
+                        Self::send_to_one(msg.clone(), peer_map.clone(), &recipient);
+                        Self::send_to_one(msg, peer_map.clone(), &addr);
+                    } else {
+                        Self::send_to_one(
+                            Message::from(ServerMsg::Rejected {
+                                reason: "User not found".into(),
+                            }),
+                            peer_map.clone(),
+                            &addr,
+                        );
+                    }
+                }
                 UserMsg::UserJoined { user } => {
-                    let mut updated_user = user;
+                    let registered_user = peer_map
+                        .lock()
+                        .unwrap()
+                        .get(&addr)
+                        .and_then(|(_, user)| user.clone());
+                    let mut updated_user = registered_user.unwrap_or(user);
                     updated_user.addr = Some(addr);
                     Self::send_to_all(
                         Message::from((
@@ -296,40 +564,78 @@ impl ChatServer {
                         None,
                     );
                 }
-                UserMsg::SyncReq { mut user } => {
+                UserMsg::UserUpdate { mut user } => {
                     user.addr = Some(addr);
-                    peer_map.lock().unwrap().get_mut(&addr).unwrap().1 = Some(user.clone());
-
-                    let check_msg = db
+                    let registered_uid = peer_map
                         .lock()
                         .unwrap()
-                        .messages
-                        .find_one(doc! {"sender_addr": addr.to_string()});
-
-                    match check_msg {
-                        Ok(msg_result) => {
-                            if let Some(msg) = msg_result {
-                                if msg.last_username != user.id {
-                                    let update_result = db.lock().unwrap().messages.update_many(
-                                        doc! {"sender_addr": addr.to_string()},
-                                        doc! {"$set": doc! {
-                                            "last_username": user.id
-                                        }},
-                                    );
-                                    if let Err(err) = update_result {
-                                        warn!("{}", err);
-                                    }
-                                }
-                            }
-                        }
-                        Err(err) => warn!("{}", err),
+                        .get(&addr)
+                        .and_then(|(_, peer)| peer.as_ref().map(|peer| peer.uid.clone()));
+                    let Some(registered_uid) = registered_uid else {
+                        warn!("Profile update from unregistered peer {}", addr);
+                        return false;
+                    };
+                    user.uid = registered_uid;
+
+                    if !is_valid_username(&user.id) {
+                        Self::send_to_one(
+                            Message::from(ServerMsg::Rejected {
+                                reason: format!("Invalid nickname: {}", user.id),
+                            }),
+                            peer_map.clone(),
+                            &addr,
+                        );
+                        return false;
+                    }
+                    if Self::is_nick_taken(&peer_map, &user.id, addr) {
+                        Self::send_to_one(
+                            Message::from(ServerMsg::NickTaken { id: user.id }),
+                            peer_map.clone(),
+                            &addr,
+                        );
+                        return false;
+                    }
+
+                    if let Some((_, peer)) = peer_map.lock().unwrap().get_mut(&addr) {
+                        *peer = Some(user.clone());
                     }
 
+                    let room_id = room.lock().unwrap()._id.clone();
+                    Self::update_sender_history(&db, &room_id, &user);
+
+                    Self::send_to_all(
+                        Message::from((UserMsg::UserUpdate { user }, None)),
+                        peer_map.clone(),
+                        None,
+                    );
+                }
+                UserMsg::SyncReq { mut user } => {
+                    user.addr = Some(addr);
+                    if user.uid.is_empty() || Self::is_uid_taken(&peer_map, &user.uid, addr) {
+                        user.uid = Uuid::new_v4().to_string();
+                    }
+                    if !is_valid_username(&user.id) {
+                        user.id = get_unique_id();
+                    }
+                    let unique_id = Self::unique_nick(&peer_map, &user.id, addr);
+                    if unique_id != user.id {
+                        user.id = unique_id.clone();
+                        Self::send_to_one(
+                            Message::from(ServerMsg::NickAssigned { id: unique_id }),
+                            peer_map.clone(),
+                            &addr,
+                        );
+                    }
+                    peer_map.lock().unwrap().get_mut(&addr).unwrap().1 = Some(user.clone());
+
+                    let room_id = room.lock().unwrap()._id.clone();
+                    Self::update_sender_history(&db, &room_id, &user);
+
                     let messages_result = db
                         .lock()
                         .unwrap()
                         .messages
-                        .find(doc! {"room_id": &room.lock().unwrap()._id})
+                        .find(doc! {"room_id": &room_id})
                         .run();
 
                     let messages = match messages_result {
@@ -361,18 +667,433 @@ impl ChatServer {
                             .clone()
                     };
 
-                    let room_id = room.lock().unwrap()._id.clone();
+                    let stored_files = files.lock().unwrap().shared();
+                    let (room_id, topic, motd, pins) = {
+                        let room = room.lock().unwrap();
+                        (
+                            room._id.clone(),
+                            room.topic.clone(),
+                            room.motd.clone(),
+                            room.pins.clone(),
+                        )
+                    };
                     Self::send_to_one(
                         Message::from(ServerMsg::Sync {
                             messages,
                             users,
                             user_addr: addr,
                             room_id,
+                            topic,
+                            motd,
+                            pins,
+                            files: stored_files,
+                        }),
+                        peer_map.clone(),
+                        &addr,
+                    );
+                }
+                UserMsg::Edit { msg_id, content } => {
+                    let user_uid = Self::registered_user(&peer_map, addr)
+                        .map(|user| user.uid)
+                        .unwrap_or_default();
+                    let mut text_msg =
+                        match Self::find_editable_msg(&msg_id, &user_uid, &room, &db, false) {
+                            Ok(text_msg) => text_msg,
+                            Err(reason) => {
+                                Self::send_to_one(
+                                    Message::from(ServerMsg::Rejected { reason }),
+                                    peer_map.clone(),
+                                    &addr,
+                                );
+                                return false;
+                            }
+                        };
+                    text_msg.edits.push(text_msg.content);
+                    let result = db.lock().unwrap().messages.update_one(
+                        doc! {"msg_id": &msg_id, "room_id": &text_msg.room_id},
+                        doc! {"$set": doc! {
+                            "content": &content,
+                            "edits": text_msg.edits,
+                        }},
+                    );
+                    if let Err(err) = result {
+                        warn!("{}", err);
+                    }
+
+                    Self::send_to_all(
+                        Message::from(ServerMsg::MsgEdited { msg_id, content }),
+                        peer_map.clone(),
+                        None,
+                    );
+                }
+                UserMsg::Delete { msg_id } => {
+                    let is_owner = *owner_addr.lock().unwrap() == Some(addr);
+                    let user_uid = Self::registered_user(&peer_map, addr)
+                        .map(|user| user.uid)
+                        .unwrap_or_default();
+                    let text_msg =
+                        match Self::find_editable_msg(&msg_id, &user_uid, &room, &db, is_owner) {
+                            Ok(text_msg) => text_msg,
+                            Err(reason) => {
+                                Self::send_to_one(
+                                    Message::from(ServerMsg::Rejected { reason }),
+                                    peer_map.clone(),
+                                    &addr,
+                                );
+                                return false;
+                            }
+                        };
+                    let result = db.lock().unwrap().messages.update_one(
+                        doc! {"msg_id": &msg_id, "room_id": &text_msg.room_id},
+                        doc! {"$set": doc! {
+                            "content": "",
+                            "edits": Vec::<String>::new(),
+                            "deleted": true,
+                        }},
+                    );
+                    if let Err(err) = result {
+                        warn!("{}", err);
+                    }
+
+                    let was_pinned = {
+                        let mut room = room.lock().unwrap();
+                        let pins_len = room.pins.len();
+                        room.pins.retain(|pin| *pin != msg_id);
+                        room.pins.len() != pins_len
+                    };
+                    if was_pinned {
+                        Self::save_room_info(&room, &db);
+                    }
+
+                    Self::send_to_all(
+                        Message::from(ServerMsg::MsgDeleted { msg_id }),
+                        peer_map.clone(),
+                        None,
+                    );
+                }
+                UserMsg::React { msg_id, emoji } => {
+                    let user_uid = peer_map
+                        .lock()
+                        .unwrap()
+                        .get(&addr)
+                        .and_then(|(_, user)| user.as_ref().map(|user| user.uid.clone()));
+
+                    if !emoji::is_emoji(&emoji) {
+                        Self::send_to_one(
+                            Message::from(ServerMsg::Rejected {
+                                reason: format!("Unknown emoji: {}", emoji),
+                            }),
+                            peer_map.clone(),
+                            &addr,
+                        );
+                    } else if let Some(user_uid) = user_uid {
+                        let room_id = room.lock().unwrap()._id.clone();
+                        let found_msg = db
+                            .lock()
+                            .unwrap()
+                            .messages
+                            .find_one(doc! {"msg_id": &msg_id, "room_id": room_id});
+
+                        match found_msg {
+                            Ok(Some(mut text_msg)) if !text_msg.deleted => {
+                                text_msg.toggle_reaction(&emoji, &user_uid);
+                                match bson::to_bson(&text_msg.reactions) {
+                                    Ok(reactions) => {
+                                        let result = db.lock().unwrap().messages.update_one(
+                                            doc! {"msg_id": &msg_id, "room_id": &text_msg.room_id},
+                                            doc! {"$set": doc! {
+                                                "reactions": reactions
+                                            }},
+                                        );
+                                        if let Err(err) = result {
+                                            warn!("{}", err);
+                                        }
+                                    }
+                                    Err(err) => warn!("{}", err),
+                                }
+
+                                Self::send_to_all(
+                                    Message::from(ServerMsg::Reactions {
+                                        msg_id,
+                                        reactions: text_msg.reactions,
+                                    }),
+                                    peer_map.clone(),
+                                    None,
+                                );
+                            }
+                            Ok(_) => warn!("Reacted message {} not found", msg_id),
+                            Err(err) => warn!("{}", err),
+                        }
+                    }
+                }
+                UserMsg::Typing { is_typing } => {
+                    if Self::registered_user(&peer_map, addr).is_none() {
+                        warn!("Typing update from unregistered peer {}", addr);
+                        return false;
+                    }
+                    Self::send_to_all(
+                        Message::from(ServerMsg::Typing { addr, is_typing }),
+                        peer_map.clone(),
+                        Some(&addr),
+                    );
+                }
+                UserMsg::Read { msg_id } => {
+                    if Self::registered_user(&peer_map, addr).is_none() {
+                        warn!("Read receipt from unregistered peer {}", addr);
+                        return false;
+                    }
+                    Self::send_to_all(
+                        Message::from(ServerMsg::ReadReceipt { addr, msg_id }),
+                        peer_map.clone(),
+                        Some(&addr),
+                    );
+                }
+                UserMsg::Presence { presence, status } => {
+                    let is_registered = match peer_map.lock().unwrap().get_mut(&addr) {
+                        Some((_, Some(user))) => {
+                            user.presence = presence;
+                            user.status = status.clone();
+                            true
+                        }
+                        _ => false,
+                    };
+                    if !is_registered {
+                        warn!("Presence update from unregistered peer {}", addr);
+                        return false;
+                    }
+                    Self::send_to_all(
+                        Message::from(ServerMsg::PresenceChanged {
+                            addr,
+                            presence,
+                            status,
+                        }),
+                        peer_map.clone(),
+                        None,
+                    );
+                }
+                UserMsg::Topic { topic } if *owner_addr.lock().unwrap() == Some(addr) => {
+                    room.lock().unwrap().topic = topic.clone();
+                    Self::save_room_info(&room, &db);
+                    Self::send_to_all(
+                        Message::from(ServerMsg::TopicChanged { addr, topic }),
+                        peer_map.clone(),
+                        None,
+                    );
+                }
+                UserMsg::Motd { motd } if *owner_addr.lock().unwrap() == Some(addr) => {
+                    room.lock().unwrap().motd = motd.clone();
+                    Self::save_room_info(&room, &db);
+                    Self::send_to_all(
+                        Message::from(ServerMsg::MotdChanged { addr, motd }),
+                        peer_map.clone(),
+                        None,
+                    );
+                }
+                UserMsg::Topic { .. } => {
+                    Self::send_to_one(
+                        Message::from(ServerMsg::Rejected {
+                            reason: "Only the room owner can change the topic".to_string(),
+                        }),
+                        peer_map.clone(),
+                        &addr,
+                    );
+                }
+                UserMsg::Motd { .. } => {
+                    Self::send_to_one(
+                        Message::from(ServerMsg::Rejected {
+                            reason: "Only the room owner can change the message of the day"
+                                .to_string(),
                         }),
                         peer_map.clone(),
                         &addr,
                     );
                 }
+                UserMsg::Pin { msg_id } => {
+                    let rejection = if *owner_addr.lock().unwrap() != Some(addr) {
+                        Some("Only the room owner can pin messages")
+                    } else if Self::find_editable_msg(&msg_id, "", &room, &db, true).is_err() {
+                        Some("Message not found")
+                    } else {
+                        None
+                    };
+                    if let Some(reason) = rejection {
+                        Self::send_to_one(
+                            Message::from(ServerMsg::Rejected {
+                                reason: reason.to_string(),
+                            }),
+                            peer_map.clone(),
+                            &addr,
+                        );
+                        return false;
+                    }
+
+                    let pinned = {
+                        let mut room = room.lock().unwrap();
+                        if let Some(pos) = room.pins.iter().position(|pin| *pin == msg_id) {
+                            room.pins.remove(pos);
+                            false
+                        } else {
+                            room.pins.push(msg_id.clone());
+                            true
+                        }
+                    };
+                    Self::save_room_info(&room, &db);
+                    Self::send_to_all(
+                        Message::from(ServerMsg::Pinned {
+                            addr,
+                            msg_id,
+                            pinned,
+                        }),
+                        peer_map.clone(),
+                        None,
+                    );
+                }
+                UserMsg::FileOffer { mut file } => {
+                    let sender_id = peer_map
+                        .lock()
+                        .unwrap()
+                        .get(&addr)
+                        .and_then(|(_, user)| user.as_ref().map(|user| user.id.clone()));
+                    let (max_size, is_duplicate, uploads) = {
+                        let files = files.lock().unwrap();
+                        (
+                            files.max_size,
+                            files.contains(&file.file_id),
+                            files.uploads_from(addr),
+                        )
+                    };
+
+                    let rejection = match sender_id {
+                        None => Some("Unknown sender".to_string()),
+                        Some(_) if is_duplicate => Some("File id already in use".to_string()),
+                        Some(_) if uploads >= MAX_UPLOADS_PER_PEER => Some(format!(
+                            "At most {} uploads at a time",
+                            MAX_UPLOADS_PER_PEER
+                        )),
+                        Some(_) if file.size == 0 => Some("File is empty".to_string()),
+                        Some(_) if file.size > max_size => Some(format!(
+                            "File exceeds the {} MB limit",
+                            max_size / (1024 * 1024)
+                        )),
+                        Some(sender_id) => {
+                            file.sender_addr = addr;
+                            file.sender_id = sender_id;
+                            None
+                        }
+                    };
+                    if let Some(reason) = rejection {
+                        Self::send_to_one(
+                            Message::from(ServerMsg::FileRejected {
+                                file_id: file.file_id,
+                                reason,
+                            }),
+                            peer_map.clone(),
+                            &addr,
+                        );
+                    } else {
+                        files.lock().unwrap().uploads.insert(
+                            file.file_id.clone(),
+                            Upload {
+                                buffer: FileBuffer::new(file.clone()),
+                                receivers: vec![],
+                            },
+                        );
+                        Self::send_to_all(
+                            Message::from(ServerMsg::FileOffer { file }),
+                            peer_map.clone(),
+                            Some(&addr),
+                        );
+                    }
+                }
+                UserMsg::FileChunk {
+                    file_id,
+                    index,
+                    data,
+                } => {
+                    let mut files = files.lock().unwrap();
+                    let Some(upload) = files
+                        .uploads
+                        .get_mut(&file_id)
+                        .filter(|upload| upload.buffer.info.sender_addr == addr)
+                    else {
+                        return false;
+                    };
+
+                    let buffer = &mut upload.buffer;
+                    let push_result = buffer.push_chunk(index, &data).and_then(|_| {
+                        if buffer.is_complete() && !buffer.is_valid() {
+                            Err(format!("Checksum mismatch for {}", buffer.info.name))
+                        } else {
+                            Ok(buffer.is_complete())
+                        }
+                    });
+                    match push_result {
+                        Ok(is_complete) => {
+                            upload
+                                .receivers
+                                .retain(|receiver| peer_map.lock().unwrap().contains_key(receiver));
+                            for receiver in &upload.receivers {
+                                Self::send_to_one(
+                                    Message::from(ServerMsg::FileChunk {
+                                        file_id: file_id.clone(),
+                                        index,
+                                        data: data.clone(),
+                                    }),
+                                    peer_map.clone(),
+                                    receiver,
+                                );
+                            }
+                            if is_complete {
+                                if let Some(upload) = files.uploads.remove(&file_id) {
+                                    files.store(upload.buffer);
+                                }
+                            }
+                        }
+                        Err(reason) => {
+                            warn!("{}", reason);
+                            files.uploads.remove(&file_id);
+                            Self::send_to_all(
+                                Message::from(ServerMsg::FileRejected { file_id, reason }),
+                                peer_map.clone(),
+                                None,
+                            );
+                        }
+                    }
+                }
+                UserMsg::FileReq { file_id } => {
+                    let mut files = files.lock().unwrap();
+                    let buffer = if let Some(upload) = files.uploads.get_mut(&file_id) {
+                        upload.receivers.push(addr);
+                        Some(&upload.buffer)
+                    } else {
+                        files
+                            .stored
+                            .iter()
+                            .find(|file| file.info.file_id == file_id)
+                    };
+                    if let Some(buffer) = buffer {
+                        for (index, data) in buffer.chunks() {
+                            Self::send_to_one(
+                                Message::from(ServerMsg::FileChunk {
+                                    file_id: file_id.clone(),
+                                    index,
+                                    data,
+                                }),
+                                peer_map.clone(),
+                                &addr,
+                            );
+                        }
+                    } else {
+                        Self::send_to_one(
+                            Message::from(ServerMsg::FileRejected {
+                                file_id,
+                                reason: "File is no longer available".to_string(),
+                            }),
+                            peer_map.clone(),
+                            &addr,
+                        );
+                    }
+                }
                 UserMsg::BanReq { addr: banned_addr } => {
                     if let Some(owner_addr) = *owner_addr.lock().unwrap() {
                         if addr == owner_addr {
@@ -402,9 +1123,47 @@ impl ChatServer {
                         }
                     }
                 }
+                UserMsg::SearchReq { pattern } => {
+                    let messages = Self::search_msgs(&pattern, &room, &db);
+                    Self::send_to_one(
+                        Message::from(ServerMsg::SearchResults { pattern, messages }),
+                        peer_map.clone(),
+                        &addr,
+                    );
+                }
                 _ => {}
             }
         }
         false
     }
+
+    fn search_msgs(
+        pattern: &str,
+        room: &Arc<Mutex<ServerRoom>>,
+        db: &Arc<Mutex<DbRepo>>,
+    ) -> Vec<TextMessage> {
+        let Ok(pattern) = Regex::new(pattern) else {
+            return vec![];
+        };
+        let room_id = room.lock().unwrap()._id.clone();
+        let messages_result = db
+            .lock()
+            .unwrap()
+            .messages
+            .find(doc! {"room_id": room_id})
+            .run();
+
+        let mut found = match messages_result {
+            Ok(msgs) => msgs
+                .filter_map(|msg| msg.ok())
+                .filter(|msg| !msg.deleted && pattern.is_match(&msg.content))
+                .collect::<Vec<TextMessage>>(),
+            Err(err) => {
+                warn!("{}", err);
+                vec![]
+            }
+        };
+        found.drain(..found.len().saturating_sub(MAX_SEARCH_RESULTS));
+        found
+    }
 }
@@ -0,0 +1,294 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use blake2::{Blake2s256, Digest};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+pub const CHUNK_SIZE: usize = 32 * 1024;
+pub const MAX_STORED_FILES: usize = 10;
+pub const MAX_UPLOADS_PER_PEER: usize = 2;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FileInfo {
+    pub file_id: String,
+    pub name: String,
+    pub size: u64,
+    pub checksum: String,
+    pub sender_addr: SocketAddr,
+    pub sender_id: String,
+}
+
+impl FileInfo {
+    pub fn new(path: &Path, data: &[u8], sender_addr: SocketAddr, sender_id: &str) -> Self {
+        Self {
+            file_id: uuid::Uuid::new_v4().to_string(),
+            name: path.file_name().map_or("file".to_string(), |name| {
+                name.to_string_lossy().to_string()
+            }),
+            size: data.len() as u64,
+            checksum: checksum(data),
+            sender_addr,
+            sender_id: sender_id.to_string(),
+        }
+    }
+
+    pub fn chunk_count(&self) -> u64 {
+        self.size.div_ceil(CHUNK_SIZE as u64)
+    }
+
+    pub fn human_size(&self) -> String {
+        human_size(self.size)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileBuffer {
+    pub info: FileInfo,
+    pub data: Vec<u8>,
+    next_index: u64,
+}
+
+impl FileBuffer {
+    pub fn new(info: FileInfo) -> Self {
+        Self {
+            info,
+            data: vec![],
+            next_index: 0,
+        }
+    }
+
+    pub fn push_chunk(&mut self, index: u64, chunk: &str) -> Result<(), String> {
+        if index != self.next_index {
+            return Err(format!("Unexpected chunk {} of {}", index, self.info.name));
+        }
+        let bytes = BASE64.decode(chunk).map_err(|err| err.to_string())?;
+        if self.data.len() as u64 + bytes.len() as u64 > self.info.size {
+            return Err(format!("{} is larger than announced", self.info.name));
+        }
+
+        self.data.extend_from_slice(&bytes);
+        self.next_index += 1;
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.next_index == self.info.chunk_count()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.data.len() as u64 == self.info.size && checksum(&self.data) == self.info.checksum
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = (u64, String)> + '_ {
+        self.data
+            .chunks(CHUNK_SIZE)
+            .enumerate()
+            .map(|(i, chunk)| (i as u64, BASE64.encode(chunk)))
+    }
+}
+
+#[derive(Debug)]
+pub struct OutgoingFile {
+    pub buffer: FileBuffer,
+    pub next_index: u64,
+}
+
+impl OutgoingFile {
+    pub fn new(info: FileInfo, data: Vec<u8>) -> Self {
+        Self {
+            buffer: FileBuffer {
+                info,
+                data,
+                next_index: 0,
+            },
+            next_index: 0,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next_index >= self.buffer.info.chunk_count()
+    }
+
+    pub fn next_chunk(&mut self) -> Option<(u64, String)> {
+        let start = self.next_index as usize * CHUNK_SIZE;
+        if start >= self.buffer.data.len() {
+            return None;
+        }
+        let end = (start + CHUNK_SIZE).min(self.buffer.data.len());
+        let chunk = (
+            self.next_index,
+            BASE64.encode(&self.buffer.data[start..end]),
+        );
+        self.next_index += 1;
+        Some(chunk)
+    }
+
+    pub fn progress(&self) -> u64 {
+        (self.next_index * 100)
+            .checked_div(self.buffer.info.chunk_count())
+            .unwrap_or(100)
+    }
+}
+
+#[derive(Debug)]
+pub struct IncomingFile {
+    pub info: FileInfo,
+    pub path: PathBuf,
+    file: File,
+    hasher: Blake2s256,
+    written: u64,
+    next_index: u64,
+}
+
+impl IncomingFile {
+    pub fn create(info: FileInfo, dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = unique_path(dir, &info.name);
+        let file = File::create(&path)?;
+        Ok(Self {
+            info,
+            path,
+            file,
+            hasher: Blake2s256::new(),
+            written: 0,
+            next_index: 0,
+        })
+    }
+
+    pub fn push_chunk(&mut self, index: u64, chunk: &str) -> Result<(), String> {
+        if index != self.next_index {
+            return Err(format!("Unexpected chunk {} of {}", index, self.info.name));
+        }
+        let bytes = BASE64.decode(chunk).map_err(|err| err.to_string())?;
+        if self.written + bytes.len() as u64 > self.info.size {
+            return Err(format!("{} is larger than announced", self.info.name));
+        }
+
+        self.file.write_all(&bytes).map_err(|err| err.to_string())?;
+        self.hasher.update(&bytes);
+        self.written += bytes.len() as u64;
+        self.next_index += 1;
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.next_index == self.info.chunk_count()
+    }
+
+    pub fn progress(&self) -> u64 {
+        (self.next_index * 100)
+            .checked_div(self.info.chunk_count())
+            .unwrap_or(100)
+    }
+
+    pub fn finish(mut self) -> Result<PathBuf, String> {
+        let is_valid = self.written == self.info.size
+            && to_hex(&self.hasher.finalize_reset()) == self.info.checksum;
+        if !is_valid {
+            let err = format!("Checksum mismatch, {} was discarded", self.info.name);
+            self.discard();
+            return Err(err);
+        }
+        self.file.flush().map_err(|err| err.to_string())?;
+        Ok(self.path)
+    }
+
+    pub fn discard(self) {
+        drop(self.file);
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[derive(Debug)]
+pub struct Upload {
+    pub buffer: FileBuffer,
+    pub receivers: Vec<SocketAddr>,
+}
+
+#[derive(Debug, Default)]
+pub struct FileStore {
+    pub max_size: u64,
+    pub keep_files: bool,
+    pub uploads: HashMap<String, Upload>,
+    pub stored: Vec<FileBuffer>,
+}
+
+impl FileStore {
+    pub fn new(max_size: u64, keep_files: bool) -> Self {
+        Self {
+            max_size,
+            keep_files,
+            ..Default::default()
+        }
+    }
+
+    pub fn contains(&self, file_id: &str) -> bool {
+        self.uploads.contains_key(file_id)
+            || self.stored.iter().any(|file| file.info.file_id == file_id)
+    }
+
+    pub fn uploads_from(&self, addr: SocketAddr) -> usize {
+        self.uploads
+            .values()
+            .filter(|upload| upload.buffer.info.sender_addr == addr)
+            .count()
+    }
+
+    pub fn store(&mut self, buffer: FileBuffer) {
+        if !self.keep_files {
+            return;
+        }
+        self.stored.push(buffer);
+        if self.stored.len() > MAX_STORED_FILES {
+            self.stored.remove(0);
+        }
+    }
+
+    pub fn shared(&self) -> Vec<FileInfo> {
+        self.stored.iter().map(|file| file.info.clone()).collect()
+    }
+}
+
+pub fn checksum(data: &[u8]) -> String {
+    to_hex(&Blake2s256::digest(data))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn human_size(size: u64) -> String {
+    match size {
+        size if size >= 1024 * 1024 => format!("{:.1} MB", size as f64 / (1024.0 * 1024.0)),
+        size if size >= 1024 => format!("{:.1} KB", size as f64 / 1024.0),
+        size => format!("{} B", size),
+    }
+}
+
+pub fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let name = Path::new(name)
+        .file_name()
+        .map_or("file".to_string(), |name| {
+            name.to_string_lossy().to_string()
+        });
+    let path = dir.join(&name);
+    if !path.exists() {
+        return path;
+    }
+
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => {
+            (stem.to_string(), format!(".{}", extension))
+        }
+        _ => (name.clone(), String::new()),
+    };
+    (2..)
+        .map(|n| dir.join(format!("{}_{}{}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap()
+}
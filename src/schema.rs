@@ -3,6 +3,7 @@ use enum_stringify::EnumStringify;
 use ratatui::style::Color as ratColor;
 use serde::{ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
 use std::{net::SocketAddr, str::FromStr, time::SystemTime};
+use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub enum Room {
@@ -20,6 +21,12 @@ pub struct ServerRoom {
     #[serde(deserialize_with = "des_soc_addr_vec")]
     #[serde(serialize_with = "ser_soc_addr_vec")]
     pub banned_addrs: Vec<SocketAddr>,
+    #[serde(default)]
+    pub topic: Option<String>,
+    #[serde(default)]
+    pub motd: Option<String>,
+    #[serde(default)]
+    pub pins: Vec<String>,
 }
 
 impl ServerRoom {
@@ -55,35 +62,170 @@ impl From<RoomHeader> for Room {
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct TextMessage {
+    #[serde(default)]
+    pub msg_id: String,
     pub room_id: String,
     pub sender_addr: SocketAddr,
+    #[serde(default)]
+    pub sender_uid: String,
     pub last_username: String,
     pub last_color: Color,
     pub content: String,
     pub timestamp: Option<SystemTime>,
+    #[serde(default)]
+    pub edits: Vec<String>,
+    #[serde(default)]
+    pub deleted: bool,
+    pub in_reply_to: Option<String>,
+    #[serde(default)]
+    pub reactions: Vec<Reaction>,
 }
 
 impl TextMessage {
     pub fn new(user: &User, room_id: &str, msg: &str) -> Self {
         Self {
+            msg_id: Uuid::new_v4().to_string(),
             sender_addr: user.addr.unwrap(),
+            sender_uid: user.uid.clone(),
             last_username: user.id.clone(),
             last_color: user.color.clone(),
             room_id: room_id.into(),
             content: msg.into(),
             timestamp: Some(SystemTime::now()),
+            edits: vec![],
+            deleted: false,
+            in_reply_to: None,
+            reactions: vec![],
         }
     }
+
+    pub fn toggle_reaction(&mut self, emoji: &str, user_uid: &str) {
+        if let Some(i) = self.reactions.iter().position(|r| r.emoji == emoji) {
+            let users = &mut self.reactions[i].users;
+            if let Some(j) = users.iter().position(|uid| uid == user_uid) {
+                users.remove(j);
+                if users.is_empty() {
+                    self.reactions.remove(i);
+                }
+            } else {
+                users.push(user_uid.to_string());
+            }
+        } else {
+            self.reactions.push(Reaction {
+                emoji: emoji.to_string(),
+                users: vec![user_uid.to_string()],
+            });
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Reaction {
+    pub emoji: String,
+    pub users: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct IgnoredUser {
+    pub _id: String,
+    #[serde(default)]
+    pub username: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct ReadMarker {
+    pub _id: String,
+    pub msg_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct InputHistory {
+    pub _id: String,
+    pub entries: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Config {
+    #[serde(default)]
+    pub uid: String,
     pub username: String,
     #[serde(deserialize_with = "des_soc_addr")]
     #[serde(serialize_with = "ser_soc_addr")]
     pub listener_addr: SocketAddr,
     pub color: Color,
     pub light_mode: bool,
+    #[serde(default = "default_away_after")]
+    pub away_after: u64,
+    #[serde(default)]
+    pub read_receipts: bool,
+    #[serde(default)]
+    pub highlight_words: Vec<String>,
+    #[serde(default = "default_bell")]
+    pub bell: bool,
+    #[serde(default)]
+    pub desktop_notify: DesktopNotify,
+    #[serde(default = "default_ignore_placeholder")]
+    pub ignore_placeholder: bool,
+    #[serde(default)]
+    pub download_dir: Option<String>,
+    #[serde(default = "default_max_file_size")]
+    pub max_file_size: u64,
+    #[serde(default)]
+    pub keep_files: bool,
+    #[serde(default = "default_markdown")]
+    pub markdown: bool,
+    #[serde(default)]
+    pub keymap: KeymapPreset,
+    #[serde(default)]
+    pub key_bindings: Vec<String>,
+    #[serde(default = "default_mouse")]
+    pub mouse: bool,
+}
+
+pub const DEFAULT_AWAY_AFTER: u64 = 10;
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 8;
+
+fn default_away_after() -> u64 {
+    DEFAULT_AWAY_AFTER
+}
+
+fn default_bell() -> bool {
+    true
+}
+
+fn default_ignore_placeholder() -> bool {
+    true
+}
+
+fn default_max_file_size() -> u64 {
+    DEFAULT_MAX_FILE_SIZE
+}
+
+fn default_markdown() -> bool {
+    true
+}
+
+fn default_mouse() -> bool {
+    false
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default, EnumStringify)]
+#[serde(rename_all = "lowercase")]
+#[enum_stringify(case = "lower")]
+pub enum DesktopNotify {
+    #[default]
+    Off,
+    Osc9,
+    Osc777,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default, EnumStringify)]
+#[serde(rename_all = "lowercase")]
+#[enum_stringify(case = "lower")]
+pub enum KeymapPreset {
+    #[default]
+    Default,
+    Vi,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, EnumStringify)]
@@ -153,3 +295,39 @@ fn des_soc_addr_vec<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<SocketAddr>,
         .map(|s| SocketAddr::from_str(&s).unwrap())
         .collect::<Vec<SocketAddr>>())
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Color, TextMessage};
+    use crate::network::{Presence, User};
+    use std::{net::SocketAddr, str::FromStr};
+
+    #[test]
+    fn reaction_toggling() {
+        let user = User {
+            id: "user1".into(),
+            uid: "uid1".into(),
+            addr: Some(SocketAddr::from_str("127.0.0.1:12345").unwrap()),
+            color: Color::White,
+            presence: Presence::Online,
+            status: None,
+        };
+        let mut msg = TextMessage::new(&user, "someroom", "hello");
+
+        msg.toggle_reaction("👍", "uid1");
+        msg.toggle_reaction("👍", "uid2");
+        msg.toggle_reaction("🎉", "uid1");
+        assert_eq!(msg.reactions.len(), 2);
+        assert_eq!(msg.reactions[0].emoji, "👍");
+        assert_eq!(msg.reactions[0].users, vec!["uid1", "uid2"]);
+        assert_eq!(msg.reactions[1].users, vec!["uid1"]);
+
+        msg.toggle_reaction("👍", "uid1");
+        assert_eq!(msg.reactions[0].users, vec!["uid2"]);
+
+        msg.toggle_reaction("🎉", "uid1");
+        assert_eq!(msg.reactions.len(), 1);
+        msg.toggle_reaction("👍", "uid2");
+        assert!(msg.reactions.is_empty());
+    }
+}
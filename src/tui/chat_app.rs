@@ -1,70 +1,239 @@
 use crate::{
+    db::DbRepo,
     error::AppError,
     network::{
         client::ChatClient,
         message::{MessageType, ServerMsg, UserMsg},
-        User,
+        transfer::{self, FileInfo, IncomingFile, OutgoingFile},
+        Presence, User,
     },
-    schema::TextMessage,
-    tui::ui::{ChatStyle, MsgItem, PopupState, StatefulArea, StatefulList, Tui},
+    schema::{
+        Color as UserColor, DesktopNotify, IgnoredUser, InputHistory, KeymapPreset, ReadMarker,
+        TextMessage, DEFAULT_AWAY_AFTER, DEFAULT_MAX_FILE_SIZE,
+    },
+    tui::{
+        commands::{self, Action, Command, Role},
+        completion::{self, Completion},
+        emoji,
+        keymap::{KeyAction, Keymap},
+        search::Search,
+        ui::{ChatStyle, MsgItem, PopupState, StatefulArea, StatefulList, Tui},
+    },
+    util::{is_valid_username, MAX_USERNAME_LEN},
+};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
 };
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use log::{info, warn};
-use ratatui::{prelude::*, style::Style};
+use polodb_core::{bson::doc, CollectionT};
+use ratatui::{prelude::*, style::Style, widgets::ListState};
 use regex::Regex;
 use std::collections::HashMap;
-use std::{io, net::SocketAddr};
+use std::{
+    io::{self, Write},
+    net::SocketAddr,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 use tokio::time::Duration;
 use tui_textarea::CursorMove;
 
 type IsAuthorized = bool;
 
+const ACK_EMOJI: &str = "\u{1F44D}";
+const TYPING_TIMEOUT: Duration = Duration::from_secs(5);
+const TYPING_REFRESH: Duration = Duration::from_secs(3);
+const MAX_MENTIONS: usize = 50;
+const CHUNKS_PER_TICK: usize = 4;
+const MAX_INPUT_HISTORY: usize = 100;
+
 pub struct ChatApp<'a> {
     pub running: bool,
     pub style: ChatStyle,
     pub client: ChatClient,
+    is_owner: bool,
     pub users: HashMap<SocketAddr, User>,
-    pub messages: StatefulList<Text<'a>>,
+    pub messages: StatefulList<MsgItem<'a>>,
     pub current_popup: PopupState,
+    pub keymap: Keymap,
     pub msg_area: StatefulArea<'a>,
-    pub commands: Vec<Command>,
+    pub last_whisper_addr: Option<SocketAddr>,
+    pub reply_to: Option<TextMessage>,
+    pub selected_msg: Option<TextMessage>,
+    pub typing_users: HashMap<SocketAddr, Instant>,
+    typing_sent_at: Option<Instant>,
+    last_keystroke_at: Option<Instant>,
+    pub away_after: Option<Duration>,
+    last_activity_at: Instant,
+    auto_away: bool,
+    pub read_receipts: bool,
+    last_read_id: Option<String>,
+    receipts: HashMap<SocketAddr, String>,
+    pub mentions: Vec<(usize, String)>,
+    pub mentions_state: ListState,
+    bell: bool,
+    desktop_notify: DesktopNotify,
+    pub ignored: HashMap<String, String>,
+    ignore_placeholder: bool,
+    hidden_msgs: Option<(usize, usize)>,
+    pub topic: Option<String>,
+    pub pins: Vec<String>,
+    pub pins_state: ListState,
+    pub emoji_query: String,
+    pub emoji_state: ListState,
+    pub completion: Option<Completion>,
+    pub search: Option<Search>,
+    pub search_input: Option<String>,
+    history: Vec<String>,
+    history_pos: Option<usize>,
+    history_draft: String,
+    compose: bool,
+    pub mouse: bool,
+    pub messages_area: Rect,
+    followed_len: usize,
+    pub user_list_area: Option<Rect>,
+    pub user_list_rows: Vec<SocketAddr>,
+    download_dir: PathBuf,
+    max_file_size: u64,
+    outgoing: Option<OutgoingFile>,
+    incoming: HashMap<String, IncomingFile>,
+    available: Vec<FileInfo>,
+    db: Arc<Mutex<DbRepo>>,
 }
 
 impl<'a> ChatApp<'a> {
-    pub fn new(client: ChatClient, light_mode: bool) -> Self {
-        let style = if !light_mode {
+    pub fn new(
+        client: ChatClient,
+        light_mode: bool,
+        db: Arc<Mutex<DbRepo>>,
+        is_owner: bool,
+    ) -> Self {
+        let config = match db.lock().unwrap().local_data.find_one(doc! {}) {
+            Ok(config) => config,
+            Err(err) => {
+                warn!("{}", err);
+                None
+            }
+        };
+
+        let mut style = if !light_mode {
             ChatStyle::new(
                 Style::new().bg(Color::Rgb(0, 0, 0)).fg(Color::White),
                 Style::new().fg(Color::Yellow),
             )
         } else {
-            ChatStyle::new(
+            let mut style = ChatStyle::new(
                 Style::new().bg(Color::White).fg(Color::Rgb(0, 0, 0)),
                 Style::new().fg(Color::Yellow),
-            )
+            );
+            style.code = Style::new().bg(Color::Rgb(225, 225, 225));
+            style.light_mode = true;
+            style
+        };
+        if let Some(config) = &config {
+            style.highlight_words = config.highlight_words.clone();
+            style.markdown = config.markdown;
+        }
+        let away_after = config
+            .as_ref()
+            .map_or(DEFAULT_AWAY_AFTER, |config| config.away_after);
+        let ignored = match db.lock().unwrap().ignored_users.find(doc! {}).run() {
+            Ok(ignored_users) => ignored_users
+                .filter_map(|ignored_user| ignored_user.ok())
+                .map(|ignored_user| (ignored_user._id, ignored_user.username))
+                .collect::<HashMap<String, String>>(),
+            Err(err) => {
+                warn!("{}", err);
+                HashMap::new()
+            }
         };
 
         Self {
             running: true,
             style: style.clone(),
             client,
+            is_owner,
             users: HashMap::new(),
             messages: StatefulList::default(),
             msg_area: StatefulArea::new(style),
             current_popup: PopupState::None,
-            commands: vec![(Regex::new(r"/ban\s+(\S+)").unwrap(), Action::Ban)],
+            keymap: config
+                .as_ref()
+                .map_or(Keymap::new(KeymapPreset::Default, &[]), |config| {
+                    Keymap::new(config.keymap, &config.key_bindings)
+                }),
+            last_whisper_addr: None,
+            reply_to: None,
+            selected_msg: None,
+            typing_users: HashMap::new(),
+            typing_sent_at: None,
+            last_keystroke_at: None,
+            away_after: (away_after > 0).then(|| Duration::from_secs(away_after * 60)),
+            last_activity_at: Instant::now(),
+            auto_away: false,
+            read_receipts: config.as_ref().is_some_and(|config| config.read_receipts),
+            last_read_id: None,
+            receipts: HashMap::new(),
+            mentions: vec![],
+            mentions_state: ListState::default(),
+            bell: config.as_ref().is_none_or(|config| config.bell),
+            desktop_notify: config
+                .as_ref()
+                .map_or(DesktopNotify::Off, |config| config.desktop_notify),
+            ignored,
+            ignore_placeholder: config
+                .as_ref()
+                .is_none_or(|config| config.ignore_placeholder),
+            hidden_msgs: None,
+            topic: None,
+            pins: vec![],
+            pins_state: ListState::default(),
+            emoji_query: String::new(),
+            emoji_state: ListState::default(),
+            completion: None,
+            search: None,
+            search_input: None,
+            history: vec![],
+            history_pos: None,
+            history_draft: String::new(),
+            compose: false,
+            mouse: config.as_ref().is_some_and(|config| config.mouse),
+            messages_area: Rect::default(),
+            followed_len: 0,
+            user_list_area: None,
+            user_list_rows: vec![],
+            download_dir: config
+                .as_ref()
+                .and_then(|config| config.download_dir.clone())
+                .map(PathBuf::from)
+                .or_else(dirs::download_dir)
+                .unwrap_or(PathBuf::from(".")),
+            max_file_size: config
+                .as_ref()
+                .map_or(DEFAULT_MAX_FILE_SIZE, |config| config.max_file_size)
+                * 1024
+                * 1024,
+            outgoing: None,
+            incoming: HashMap::new(),
+            available: vec![],
+            db,
         }
     }
 
     pub async fn run(&mut self) -> Result<(), AppError> {
         let terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
         let mut tui = Tui::new(terminal);
-        tui.term_init()?;
+        tui.term_init(self.mouse)?;
 
         while self.running {
             if self.client.is_ok() && !self.handle_msgs().await {
                 return Err(AppError::AuthFailure);
             }
+            self.update_typing().await;
+            self.update_presence().await;
+            self.send_file_chunks().await;
             tui.draw(self)?;
             self.handle_input().await?;
         }
@@ -77,6 +246,44 @@ impl<'a> ChatApp<'a> {
         if event::poll(Duration::from_millis(10))? {
             let key_event = event::read()?;
 
+            if let Event::Mouse(mouse_event) = key_event {
+                self.handle_mouse(mouse_event);
+                return Ok(());
+            }
+
+            if let Event::Key(_) = key_event {
+                self.last_activity_at = Instant::now();
+                if self.auto_away {
+                    self.auto_away = false;
+                    self.send_presence(Presence::Online, None).await;
+                }
+            }
+
+            if let Event::Key(KeyEvent {
+                code, modifiers, ..
+            }) = key_event
+            {
+                if code != KeyCode::BackTab
+                    && self.keymap.action(code, modifiers, false) != Some(KeyAction::Complete)
+                {
+                    self.completion = None;
+                }
+                if self.search_input.is_some() && self.handle_search_input(code, modifiers).await {
+                    return Ok(());
+                }
+                if self.current_popup == PopupState::Emoji
+                    && self.handle_emoji_input(code, modifiers)
+                {
+                    return Ok(());
+                }
+                if self.current_popup == PopupState::Mentions && self.handle_mentions_input(code) {
+                    return Ok(());
+                }
+                if self.current_popup == PopupState::Pins && self.handle_pins_input(code) {
+                    return Ok(());
+                }
+            }
+
             // this has to be fixed
             if let Event::Key(_) = key_event {
                 if self.current_popup != PopupState::None {
@@ -87,6 +294,17 @@ impl<'a> ChatApp<'a> {
                 code, modifiers, ..
             }) = key_event
             {
+                if let Some(action) = self
+                    .keymap
+                    .action(code, modifiers, self.messages.is_highlighted)
+                    .filter(|action| {
+                        self.search.is_some()
+                            || !matches!(action, KeyAction::SearchNext | KeyAction::SearchPrevious)
+                    })
+                {
+                    self.handle_key_action(action).await;
+                    return Ok(());
+                }
                 match code {
                     KeyCode::Left => {
                         self.msg_area.textarea.move_cursor(CursorMove::Back);
@@ -94,54 +312,31 @@ impl<'a> ChatApp<'a> {
                     KeyCode::Right => {
                         self.msg_area.textarea.move_cursor(CursorMove::Forward);
                     }
-                    KeyCode::Up => {
-                        self.msg_area.textarea.move_cursor(CursorMove::Up);
-                    }
-                    KeyCode::Down => {
-                        self.msg_area.textarea.move_cursor(CursorMove::Down);
-                    }
-                    KeyCode::Char('k') if modifiers.contains(KeyModifiers::CONTROL) => {
-                        self.messages.is_highlighted = true;
-                        self.messages.previous();
-                    }
-                    KeyCode::Char('j') if modifiers.contains(KeyModifiers::CONTROL) => {
-                        self.messages.is_highlighted = true;
-                        self.messages.next();
-                    }
-                    KeyCode::Char('q') if modifiers.contains(KeyModifiers::CONTROL) => {
-                        self.client.disconnect();
-                        self.running = false;
+                    KeyCode::Enter
+                        if self.compose
+                            && !modifiers.intersects(KeyModifiers::ALT | KeyModifiers::CONTROL) =>
+                    {
+                        self.msg_area.textarea.insert_newline();
                     }
                     KeyCode::Enter => {
                         if self.client.is_ok() {
                             self.handle_text_buffer().await;
                         }
                     }
-                    KeyCode::Char('l') if modifiers.contains(KeyModifiers::CONTROL) => {
-                        if self.current_popup == PopupState::List {
-                            self.current_popup = PopupState::None;
-                        } else {
-                            self.current_popup = PopupState::List;
-                        }
-                    }
-                    KeyCode::Char('h') if modifiers.contains(KeyModifiers::CONTROL) => {
-                        if self.current_popup == PopupState::Help {
-                            self.current_popup = PopupState::None;
-                        }
-                        self.current_popup = PopupState::Help;
-                    }
-                    KeyCode::Char('y') if modifiers.contains(KeyModifiers::CONTROL) => {
-                        self.msg_area.textarea.copy();
-                    }
-                    KeyCode::Char('p') if modifiers.contains(KeyModifiers::CONTROL) => {
-                        _ = self.msg_area.textarea.paste();
-                    }
                     KeyCode::Backspace => {
                         self.handle_deleting_chars();
                     }
+                    KeyCode::BackTab => {
+                        self.complete(false);
+                    }
                     _ => {
+                        if self.messages.is_highlighted {
+                            self.selected_msg = self.highlighted_msg();
+                        }
                         self.messages.is_highlighted = false;
-                        self.msg_area.on_input_update(key_event.into());
+                        if self.msg_area.on_input_update(key_event.into()) {
+                            self.notify_typing().await;
+                        }
                     }
                 }
             }
@@ -149,41 +344,239 @@ impl<'a> ChatApp<'a> {
         Ok(())
     }
 
-    async fn handle_text_buffer(&mut self) {
-        self.msg_area.height = 0;
+    fn handle_mouse(&mut self, mouse_event: MouseEvent) {
+        let MouseEvent {
+            kind, column, row, ..
+        } = mouse_event;
+        match kind {
+            MouseEventKind::ScrollUp => {
+                self.messages.is_highlighted = true;
+                self.messages.previous();
+            }
+            MouseEventKind::ScrollDown => {
+                self.messages.is_highlighted = true;
+                self.messages.next();
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                if self.current_popup == PopupState::List {
+                    if let Some(user_id) = self.user_at(column, row).map(|user| user.id.clone()) {
+                        self.mention_user(&user_id);
+                    }
+                    self.current_popup = PopupState::None;
+                } else if self.current_popup != PopupState::None {
+                    self.current_popup = PopupState::None;
+                } else if let Some(i) = self.messages.item_at(self.messages_area, column, row) {
+                    self.messages.is_highlighted = true;
+                    self.messages.state.select(Some(i));
+                }
+            }
+            _ => {}
+        }
+    }
 
-        if let Some(text) = self.msg_area.get_text() {
-            if !self.parse_commands(&text).await {
-                let msg = TextMessage::new(
-                    &self.client.user.lock().unwrap(),
-                    &self.client.room.lock().unwrap()._id,
-                    &text,
-                );
+    pub fn sorted_users(&self) -> Vec<&User> {
+        let mut users = self.users.values().collect::<Vec<&User>>();
+        users.sort_by_key(|user| (user.id.to_lowercase(), user.addr));
+        users
+    }
+
+    fn user_at(&self, column: u16, row: u16) -> Option<&User> {
+        let area = self.user_list_area?;
+        if !area.contains(Position::new(column, row)) {
+            return None;
+        }
+        self.user_list_rows
+            .get((row - area.y) as usize)
+            .and_then(|addr| self.users.get(addr))
+    }
+
+    fn mention_user(&mut self, user_id: &str) {
+        self.msg_area.textarea.insert_str(format!("@{} ", user_id));
+        self.msg_area.update_height();
+        self.messages.is_highlighted = false;
+    }
 
-                let send_result = self
-                    .client
-                    .send_msg(UserMsg::Normal { msg: msg.clone() })
+    async fn handle_key_action(&mut self, action: KeyAction) {
+        match action {
+            KeyAction::Quit => {
+                self.client.disconnect();
+                self.running = false;
+            }
+            KeyAction::UserList => {
+                if self.current_popup == PopupState::List {
+                    self.current_popup = PopupState::None;
+                } else {
+                    self.current_popup = PopupState::List;
+                }
+            }
+            KeyAction::Help => {
+                if self.current_popup == PopupState::Help {
+                    self.current_popup = PopupState::None;
+                } else {
+                    self.current_popup = PopupState::Help;
+                }
+            }
+            KeyAction::Cancel => {
+                self.selected_msg = None;
+                self.search = None;
+                self.cancel_reply();
+                self.messages.is_highlighted = false;
+            }
+            KeyAction::HistoryPrevious => {
+                if !self.msg_area.move_cursor_up() {
+                    self.recall_history(true);
+                }
+            }
+            KeyAction::HistoryNext => {
+                if !self.msg_area.move_cursor_down() {
+                    self.recall_history(false);
+                }
+            }
+            KeyAction::Send => {
+                if self.client.is_ok() {
+                    self.handle_text_buffer().await;
+                }
+            }
+            KeyAction::Complete => {
+                self.complete(true);
+            }
+            KeyAction::ScrollUp => {
+                self.messages.is_highlighted = true;
+                self.messages.previous();
+            }
+            KeyAction::ScrollDown => {
+                self.messages.is_highlighted = true;
+                self.messages.next();
+            }
+            KeyAction::Reply => {
+                if let Some(msg) = self.highlighted_msg() {
+                    let author = self
+                        .users
+                        .get(&msg.sender_addr)
+                        .map(|user| user.id.clone())
+                        .unwrap_or(msg.last_username.clone());
+                    self.msg_area
+                        .set_title(Some(format!("replying to {} [esc]", author)));
+                    self.reply_to = Some(msg);
+                    self.messages.is_highlighted = false;
+                }
+            }
+            KeyAction::Acknowledge => {
+                if let Some(msg) = self.highlighted_msg() {
+                    self.send_or_report(UserMsg::React {
+                        msg_id: msg.msg_id,
+                        emoji: ACK_EMOJI.to_string(),
+                    })
                     .await;
-                match send_result {
-                    Ok(_) => {
-                        let user_id = self.client.user.lock().unwrap().id.clone();
-                        self.messages.items.push(MsgItem::user_msg(
-                            &msg,
-                            user_id.clone(),
-                            self.client.user.lock().unwrap().color.clone(),
-                            &self.style,
-                            user_id,
-                        ));
-                    }
-                    Err(err) => {
-                        self.messages.items.push(MsgItem::info_msg(
-                            "Failed sending message".to_string(),
-                            Color::Rgb(255, 127, 127),
-                        ));
-                        info!("{}", err);
-                    }
                 }
             }
+            KeyAction::Mentions => {
+                self.current_popup = PopupState::Mentions;
+                if self.mentions_state.selected().is_none() && !self.mentions.is_empty() {
+                    self.mentions_state.select(Some(self.mentions.len() - 1));
+                }
+            }
+            KeyAction::Pins => {
+                self.current_popup = PopupState::Pins;
+                if self.pins_state.selected().is_none() && !self.pins.is_empty() {
+                    self.pins_state.select(Some(self.pins.len() - 1));
+                }
+            }
+            KeyAction::EmojiPicker => {
+                self.current_popup = PopupState::Emoji;
+                self.emoji_query.clear();
+                self.emoji_state.select(Some(0));
+            }
+            KeyAction::Copy => {
+                self.msg_area.textarea.copy();
+            }
+            KeyAction::Paste => {
+                _ = self.msg_area.textarea.paste();
+            }
+            KeyAction::ScrollTop => {
+                self.messages.is_highlighted = true;
+                self.messages.state.select(Some(0));
+            }
+            KeyAction::PageUp => {
+                self.messages.is_highlighted = true;
+                self.messages.page_up(self.messages_area.height);
+            }
+            KeyAction::PageDown => {
+                self.messages.is_highlighted = true;
+                self.messages.page_down(self.messages_area.height);
+            }
+            KeyAction::ScrollBottom => {
+                self.messages.is_highlighted = false;
+                self.messages.select_last();
+            }
+            KeyAction::Search => {
+                self.search_input = Some(String::new());
+            }
+            KeyAction::SearchNext => {
+                self.search_step(true);
+            }
+            KeyAction::SearchPrevious => {
+                self.search_step(false);
+            }
+            KeyAction::FocusInput => {
+                self.selected_msg = self.highlighted_msg();
+                self.messages.is_highlighted = false;
+            }
+        }
+    }
+
+    async fn handle_text_buffer(&mut self) {
+        self.stop_typing().await;
+
+        if let Some(text) = self.msg_area.get_text() {
+            self.record_input(&text);
+            match commands::parse(&text) {
+                Some(Ok((command, args))) => self.run_command(command, args).await,
+                Some(Err(err)) => {
+                    self.messages
+                        .items
+                        .push(MsgItem::info_msg(err, Color::Rgb(255, 127, 127)));
+                    self.msg_area.textarea.insert_str(&text);
+                }
+                None => {
+                    let text = text
+                        .strip_prefix('/')
+                        .filter(|text| text.starts_with('/'))
+                        .unwrap_or(&text);
+                    self.send_text(text).await;
+                }
+            }
+        }
+        self.selected_msg = None;
+    }
+
+    async fn send_text(&mut self, text: &str) {
+        let text = emoji::expand_shortcodes(text);
+        let mut msg = TextMessage::new(
+            &self.client.user.lock().unwrap(),
+            &self.client.room.lock().unwrap()._id,
+            &text,
+        );
+        msg.in_reply_to = self.reply_to.as_ref().map(|parent| parent.msg_id.clone());
+        self.cancel_reply();
+
+        let send_result = self
+            .client
+            .send_msg(UserMsg::Normal { msg: msg.clone() })
+            .await;
+        match send_result {
+            Ok(_) => {
+                let user = self.client.user.lock().unwrap().clone();
+                let item = self.user_msg_item(&msg, user.id, user.color);
+                self.messages.items.push(item);
+            }
+            Err(err) => {
+                self.messages.items.push(MsgItem::info_msg(
+                    "Failed sending message".to_string(),
+                    Color::Rgb(255, 127, 127),
+                ));
+                info!("{}", err);
+            }
         }
     }
 
@@ -192,17 +585,93 @@ impl<'a> ChatApp<'a> {
             match msg_type {
                 MessageType::User(user_msg) => match user_msg {
                     UserMsg::Normal { msg } => {
-                        let user = self.users.get(&msg.sender_addr).unwrap();
-                        self.messages.items.push(MsgItem::user_msg(
+                        self.typing_users.remove(&msg.sender_addr);
+                        let is_known = self.messages.items.iter().any(|item| {
+                            item.msg
+                                .as_ref()
+                                .is_some_and(|known| known.msg_id == msg.msg_id)
+                        });
+                        if is_known {
+                            warn!("Duplicate message id {}", msg.msg_id);
+                            return true;
+                        }
+                        let (id, color) = match self.users.get(&msg.sender_addr) {
+                            Some(user) => (user.id.clone(), user.color.clone()),
+                            None => (msg.last_username.clone(), msg.last_color.clone()),
+                        };
+                        if self.ignored.contains_key(&msg.sender_uid) {
+                            self.hide_msg();
+                            return true;
+                        }
+                        let item = self.user_msg_item(&msg, id.clone(), color);
+                        self.messages.items.push(item);
+                        if self.is_mention(&msg.content) {
+                            self.record_mention(&id, &msg.content);
+                        }
+                    }
+                    UserMsg::Private { msg, recipient } => {
+                        let own_user = self.client.user.lock().unwrap().clone();
+                        if own_user.addr == Some(msg.sender_addr) {
+                            let recipient_id = self
+                                .users
+                                .get(&recipient)
+                                .map_or_else(|| recipient.to_string(), |user| user.id.clone());
+                            self.messages.items.push(MsgItem::private_msg(
+                                &msg,
+                                own_user.id,
+                                own_user.color,
+                                recipient_id,
+                            ));
+                            return true;
+                        }
+                        let (id, color) = match self.users.get(&msg.sender_addr) {
+                            Some(user) => (user.id.clone(), user.color.clone()),
+                            None => (msg.last_username.clone(), msg.last_color.clone()),
+                        };
+                        if self.ignored.contains_key(&msg.sender_uid) {
+                            return true;
+                        }
+                        self.messages.items.push(MsgItem::private_msg(
                             &msg,
-                            user.id.clone(),
-                            user.color.clone(),
-                            &self.style,
+                            id.clone(),
+                            color,
                             self.client.user.lock().unwrap().id.clone(),
                         ));
+                        self.last_whisper_addr = Some(msg.sender_addr);
+                        self.record_mention(&format!("{} [whisper]", id), &msg.content);
+                    }
+                    UserMsg::UserUpdate { user } => {
+                        let addr = user.addr.unwrap();
+                        let old_user = self
+                            .users
+                            .insert(addr, user.clone())
+                            .filter(|old_user| !self.ignored.contains_key(&old_user.uid));
+                        if let Some(old_user) = old_user {
+                            if old_user.id != user.id {
+                                self.messages.items.push(MsgItem::info_msg(
+                                    format!("{} is now {}", old_user.id, user.id),
+                                    Color::Rgb(75, 75, 75),
+                                ));
+                            }
+                            if old_user.color != user.color {
+                                self.messages.items.push(MsgItem::info_msg(
+                                    format!("{} is now {}", user.id, user.color),
+                                    Color::Rgb(75, 75, 75),
+                                ));
+                            }
+                        }
+
+                        let mut own_user = self.client.user.lock().unwrap();
+                        if own_user.addr == Some(addr) {
+                            own_user.id = user.id;
+                            own_user.color = user.color;
+                        }
                     }
                     UserMsg::UserJoined { user } => {
                         self.users.insert(user.addr.unwrap(), user.clone());
+                        if self.ignored.contains_key(&user.uid) {
+                            return true;
+                        }
 
                         self.messages.items.push(MsgItem::info_msg(
                             format!("{} has joined", user.id),
@@ -213,8 +682,17 @@ impl<'a> ChatApp<'a> {
                 },
                 MessageType::Server(server_msg) => match server_msg {
                     ServerMsg::Sync {
-                        messages, users, ..
+                        messages,
+                        users,
+                        topic,
+                        motd,
+                        pins,
+                        files,
+                        ..
                     } => {
+                        self.topic = topic;
+                        self.pins = pins;
+                        self.available = files;
                         self.users.extend(
                             users
                                 .into_iter()
@@ -222,34 +700,85 @@ impl<'a> ChatApp<'a> {
                                 .collect::<HashMap<SocketAddr, User>>(),
                         );
 
-                        self.messages.items.append(
-                            &mut messages
-                                .into_iter()
-                                .map(|msg| {
-                                    let (id, color) =
-                                        if let Some(user) = self.users.get(&msg.sender_addr) {
-                                            (user.id.clone(), user.color.clone())
-                                        } else {
-                                            (msg.last_username.clone(), msg.last_color.clone())
-                                        };
-
-                                    MsgItem::user_msg(
-                                        &msg,
-                                        id,
-                                        color,
-                                        &self.style,
-                                        self.client.user.lock().unwrap().id.clone(),
-                                    )
-                                })
-                                .collect::<Vec<Text>>(),
-                        );
+                        let own_uid = self.client.user.lock().unwrap().uid.clone();
+                        for msg in messages {
+                            let (id, color) = if let Some(user) = self.users.get(&msg.sender_addr) {
+                                (user.id.clone(), user.color.clone())
+                            } else {
+                                (msg.last_username.clone(), msg.last_color.clone())
+                            };
+                            if self.ignored.contains_key(&msg.sender_uid) {
+                                self.hide_msg();
+                                continue;
+                            }
+                            let item = self.user_msg_item(&msg, id.clone(), color);
+                            self.messages.items.push(item);
+                            // History is scanned without alerting for every past mention
+                            if msg.sender_uid != own_uid && self.is_mention(&msg.content) {
+                                self.push_mention(&id, &msg.content);
+                            }
+                        }
+                        self.insert_unread_divider();
+                        self.load_input_history();
+                        if let Some(motd) = motd {
+                            self.messages.items.push(MsgItem::info_msg(
+                                format!("Message of the day: {}", motd),
+                                Color::Rgb(75, 75, 75),
+                            ));
+                        }
+                        if !self.available.is_empty() {
+                            self.messages.items.push(MsgItem::info_msg(
+                                format!(
+                                    "{} shared file(s) available: {}, /fetch [name] to download",
+                                    self.available.len(),
+                                    self.available
+                                        .iter()
+                                        .map(|file| file.name.clone())
+                                        .collect::<Vec<String>>()
+                                        .join(", ")
+                                ),
+                                Color::Rgb(75, 75, 75),
+                            ));
+                        }
+                    }
+                    ServerMsg::TopicChanged { addr, topic } => {
+                        let author = self
+                            .users
+                            .get(&addr)
+                            .map_or(addr.to_string(), |user| user.id.clone());
+                        let info = match &topic {
+                            Some(topic) => format!("{} changed the topic to: {}", author, topic),
+                            None => format!("{} cleared the topic", author),
+                        };
+                        self.messages
+                            .items
+                            .push(MsgItem::info_msg(info, Color::Rgb(75, 75, 75)));
+                        self.topic = topic;
+                    }
+                    ServerMsg::MotdChanged { addr, motd } => {
+                        let author = self
+                            .users
+                            .get(&addr)
+                            .map_or(addr.to_string(), |user| user.id.clone());
+                        let info = match motd {
+                            Some(motd) => {
+                                format!("{} changed the message of the day to: {}", author, motd)
+                            }
+                            None => format!("{} cleared the message of the day", author),
+                        };
+                        self.messages
+                            .items
+                            .push(MsgItem::info_msg(info, Color::Rgb(75, 75, 75)));
                     }
                     ServerMsg::UserLeft { addr } => {
-                        self.messages.items.push(MsgItem::info_msg(
-                            format!("{} has left", self.users.get(&addr).unwrap().id),
-                            Color::Rgb(75, 75, 75),
-                        ));
-                        self.users.remove(&addr).unwrap();
+                        self.typing_users.remove(&addr);
+                        let user = self.users.remove(&addr).unwrap();
+                        if !self.ignored.contains_key(&user.uid) {
+                            self.messages.items.push(MsgItem::info_msg(
+                                format!("{} has left", user.id),
+                                Color::Rgb(75, 75, 75),
+                            ));
+                        }
                     }
                     ServerMsg::BanConfirm { addr } => {
                         if addr == self.client.user.lock().unwrap().addr.unwrap() {
@@ -265,6 +794,163 @@ impl<'a> ChatApp<'a> {
                             ));
                         }
                     }
+                    ServerMsg::NickTaken { id } => {
+                        self.messages.items.push(MsgItem::info_msg(
+                            format!("Nickname {} is already taken", id),
+                            Color::Rgb(255, 127, 127),
+                        ));
+                    }
+                    ServerMsg::NickAssigned { id } => {
+                        self.messages.items.push(MsgItem::info_msg(
+                            format!("Your nickname is unavailable, you are now {}", id),
+                            Color::Rgb(75, 75, 75),
+                        ));
+                    }
+                    ServerMsg::MsgEdited { msg_id, content } => {
+                        self.update_msg_item(&msg_id, |msg| {
+                            msg.edits.push(std::mem::replace(&mut msg.content, content));
+                        });
+                    }
+                    ServerMsg::Typing { addr, is_typing } => {
+                        if is_typing && !self.is_ignored_addr(&addr) {
+                            self.typing_users.insert(addr, Instant::now());
+                        } else {
+                            self.typing_users.remove(&addr);
+                        }
+                    }
+                    ServerMsg::PresenceChanged {
+                        addr,
+                        presence,
+                        status,
+                    } => {
+                        if let Some(user) = self.users.get_mut(&addr) {
+                            user.presence = presence;
+                            user.status = status.clone();
+
+                            let status_suffix =
+                                status.map(|s| format!(": {}", s)).unwrap_or_default();
+                            let info = match presence {
+                                Presence::Online => format!("{} is back", user.id),
+                                Presence::Away => format!("{} is away{}", user.id, status_suffix),
+                                Presence::DoNotDisturb => {
+                                    format!("{} is busy{}", user.id, status_suffix)
+                                }
+                            };
+                            if !self.ignored.contains_key(&user.uid) {
+                                self.messages
+                                    .items
+                                    .push(MsgItem::info_msg(info, Color::Rgb(75, 75, 75)));
+                            }
+                        }
+
+                        let mut own_user = self.client.user.lock().unwrap();
+                        if own_user.addr == Some(addr) {
+                            own_user.presence = presence;
+                            own_user.status = self.users.get(&addr).and_then(|u| u.status.clone());
+                        }
+                    }
+                    ServerMsg::ReadReceipt { addr, msg_id } => {
+                        self.receipts.insert(addr, msg_id);
+                        self.apply_read_receipts();
+                    }
+                    ServerMsg::Reactions { msg_id, reactions } => {
+                        self.update_msg_item(&msg_id, |msg| msg.reactions = reactions);
+                    }
+                    ServerMsg::Pinned {
+                        addr,
+                        msg_id,
+                        pinned,
+                    } => {
+                        let author = self
+                            .users
+                            .get(&addr)
+                            .map_or(addr.to_string(), |user| user.id.clone());
+                        if pinned {
+                            self.pins.push(msg_id);
+                        } else {
+                            self.pins.retain(|pin| *pin != msg_id);
+                            self.pins_state.select(None);
+                        }
+                        self.messages.items.push(MsgItem::info_msg(
+                            format!(
+                                "{} {} a message",
+                                author,
+                                if pinned { "pinned" } else { "unpinned" }
+                            ),
+                            Color::Rgb(75, 75, 75),
+                        ));
+                    }
+                    ServerMsg::FileOffer { file }
+                        if !self.is_ignored_addr(&file.sender_addr)
+                            && file.size <= self.max_file_size =>
+                    {
+                        let info = format!(
+                            "{} is sharing {} ({}), /accept to save or /decline",
+                            file.sender_id,
+                            file.name,
+                            file.human_size()
+                        );
+                        self.messages
+                            .items
+                            .push(MsgItem::info_msg(info, Color::Rgb(75, 75, 75)));
+                        self.available.retain(|offer| offer.file_id != file.file_id);
+                        self.available.push(file);
+                    }
+                    ServerMsg::FileChunk {
+                        file_id,
+                        index,
+                        data,
+                    } => {
+                        self.receive_file_chunk(&file_id, index, &data);
+                    }
+                    ServerMsg::FileRejected { file_id, reason } => {
+                        let file_name = if self
+                            .outgoing
+                            .as_ref()
+                            .is_some_and(|outgoing| outgoing.buffer.info.file_id == file_id)
+                        {
+                            self.outgoing
+                                .take()
+                                .map(|outgoing| outgoing.buffer.info.name)
+                        } else {
+                            let offer = self
+                                .available
+                                .iter()
+                                .position(|file| file.file_id == file_id)
+                                .map(|pos| self.available.remove(pos).name);
+                            self.incoming
+                                .remove(&file_id)
+                                .map(|incoming| {
+                                    let name = incoming.info.name.clone();
+                                    incoming.discard();
+                                    name
+                                })
+                                .or(offer)
+                        };
+                        if let Some(file_name) = file_name {
+                            self.messages.items.push(MsgItem::info_msg(
+                                format!("Transfer of {} failed: {}", file_name, reason),
+                                Color::Rgb(255, 127, 127),
+                            ));
+                        }
+                    }
+                    ServerMsg::MsgDeleted { msg_id } => {
+                        self.pins.retain(|pin| *pin != msg_id);
+                        self.pins_state.select(None);
+                        self.update_msg_item(&msg_id, |msg| {
+                            msg.content.clear();
+                            msg.edits.clear();
+                            msg.deleted = true;
+                        });
+                    }
+                    ServerMsg::SearchResults { pattern, messages } => {
+                        self.show_search_results(pattern, messages);
+                    }
+                    ServerMsg::Rejected { reason } => {
+                        self.messages
+                            .items
+                            .push(MsgItem::info_msg(reason, Color::Rgb(255, 127, 127)));
+                    }
                     ServerMsg::ServerShutdown => {
                         self.messages.items.push(MsgItem::info_msg(
                             String::from("Server has been shutted down."),
@@ -279,58 +965,1622 @@ impl<'a> ChatApp<'a> {
                 },
             }
         }
+        if let Some(search) = self.search.as_mut() {
+            search.refresh(&self.messages.items);
+        }
         if !self.messages.is_highlighted {
             self.messages.select_last();
+            self.followed_len = self.messages.items.len();
+            self.mark_read().await;
         }
         true
     }
 
-    fn handle_deleting_chars(&mut self) {
-        if self.msg_area.textarea.cursor().1 == 0 && self.msg_area.textarea.cursor().0 > 0 {
-            self.msg_area.textarea.delete_newline();
-            self.msg_area.height -= 1;
+    fn insert_msg_item(&mut self, pos: usize, item: MsgItem<'a>) {
+        self.messages.items.insert(pos, item);
+        let shift = |i: &mut usize| {
+            if *i >= pos {
+                *i += 1;
+            }
+        };
+        self.mentions
+            .iter_mut()
+            .for_each(|(item_pos, _)| shift(item_pos));
+        if let Some((hidden_pos, _)) = self.hidden_msgs.as_mut() {
+            shift(hidden_pos);
+        }
+        if let Some(selected) = self.messages.state.selected_mut().as_mut() {
+            shift(selected);
+        }
+        if pos < self.followed_len {
+            self.followed_len += 1;
+        }
+    }
+
+    fn hide_msg(&mut self) {
+        if !self.ignore_placeholder {
+            return;
+        }
+
+        let count = match self.hidden_msgs {
+            Some((pos, count)) if pos + 1 == self.messages.items.len() => {
+                self.messages.items.pop();
+                count + 1
+            }
+            _ => 1,
+        };
+        self.messages.items.push(MsgItem::info_msg(
+            format!(
+                "{} hidden message{}",
+                count,
+                if count == 1 { "" } else { "s" }
+            ),
+            Color::Rgb(75, 75, 75),
+        ));
+        self.hidden_msgs = Some((self.messages.items.len() - 1, count));
+    }
+
+    fn set_ignored(&mut self, uid: &str, username: &str, ignore: bool) {
+        let db = self.db.lock().unwrap();
+        let result = if ignore {
+            self.ignored.insert(uid.to_string(), username.to_string());
+            db.ignored_users
+                .insert_one(IgnoredUser {
+                    _id: uid.to_string(),
+                    username: username.to_string(),
+                })
+                .map(|_| ())
         } else {
-            self.msg_area.textarea.delete_char();
+            self.ignored.remove(uid);
+            db.ignored_users.delete_one(doc! {"_id": uid}).map(|_| ())
+        };
+        if let Err(err) = result {
+            warn!("{}", err);
         }
     }
 
-    async fn parse_commands(&self, haystack: &str) -> bool {
-        for command in self.commands.iter() {
-            if let Some(args) = Self::parse_command(command, haystack) {
-                match command.1 {
-                    Action::Ban => {
-                        if let Some(user_addr) =
-                            self.users.iter().find(|(_, user)| user.id == args[1])
-                        {
-                            self.client
-                                .ban(&user_addr.1.addr.unwrap())
-                                .await
-                                .unwrap_or_else(|err| warn!("{}", err));
-                        }
-                    }
-                }
-                return true;
+    fn is_ignored_addr(&self, addr: &SocketAddr) -> bool {
+        self.users
+            .get(addr)
+            .is_some_and(|user| self.ignored.contains_key(&user.uid))
+    }
+
+    fn is_mention(&self, content: &str) -> bool {
+        let target_user = self.client.user.lock().unwrap().id.clone();
+        Regex::new(&self.style.highlight_pattern(&target_user))
+            .is_ok_and(|pattern| pattern.is_match(content))
+    }
+
+    fn record_mention(&mut self, author: &str, content: &str) {
+        self.push_mention(author, content);
+        self.alert(author, content.lines().next().unwrap_or_default());
+    }
+
+    fn push_mention(&mut self, author: &str, content: &str) {
+        let first_line = content.lines().next().unwrap_or_default();
+        self.mentions.push((
+            self.messages.items.len() - 1,
+            format!("{}: {}", author, first_line),
+        ));
+        if self.mentions.len() > MAX_MENTIONS {
+            self.mentions.remove(0);
+        }
+    }
+
+    fn alert(&self, title: &str, body: &str) {
+        if self.client.user.lock().unwrap().presence == Presence::DoNotDisturb {
+            return;
+        }
+
+        let sanitize = |s: &str| {
+            s.chars()
+                .filter(|c| !c.is_control() && *c != ';')
+                .collect::<String>()
+        };
+        let mut sequence = String::new();
+        if self.bell {
+            sequence.push('\x07');
+        }
+        match self.desktop_notify {
+            DesktopNotify::Osc9 => {
+                sequence.push_str(&format!(
+                    "\x1b]9;{}: {}\x07",
+                    sanitize(title),
+                    sanitize(body)
+                ));
+            }
+            DesktopNotify::Osc777 => {
+                sequence.push_str(&format!(
+                    "\x1b]777;notify;{};{}\x07",
+                    sanitize(title),
+                    sanitize(body)
+                ));
             }
+            DesktopNotify::Off => {}
         }
 
-        false
+        if !sequence.is_empty() {
+            let mut stdout = io::stdout();
+            if let Err(err) = stdout
+                .write_all(sequence.as_bytes())
+                .and_then(|_| stdout.flush())
+            {
+                warn!("{}", err);
+            }
+        }
     }
 
-    fn parse_command(command: &Command, haystack: &str) -> Option<Vec<String>> {
-        if let Some(captures) = command.0.captures(haystack) {
-            return Some(
-                captures
-                    .iter()
-                    .map(|cap| cap.unwrap().as_str().to_string())
-                    .collect::<Vec<String>>(),
-            );
+    async fn send_file(&mut self, path: &str) {
+        if self.outgoing.is_some() {
+            self.messages.items.push(MsgItem::info_msg(
+                "Another file is still being sent".to_string(),
+                Color::Rgb(255, 127, 127),
+            ));
+            return;
+        }
+
+        let path = match path.strip_prefix("~/").zip(dirs::home_dir()) {
+            Some((relative_path, home_dir)) => home_dir.join(relative_path),
+            None => PathBuf::from(path),
+        };
+        let file_size = match tokio::fs::metadata(&path).await {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            Ok(_) => {
+                self.messages.items.push(MsgItem::info_msg(
+                    format!("{} is not a file", path.display()),
+                    Color::Rgb(255, 127, 127),
+                ));
+                return;
+            }
+            Err(err) => {
+                self.messages.items.push(MsgItem::info_msg(
+                    format!("Cannot read {}: {}", path.display(), err),
+                    Color::Rgb(255, 127, 127),
+                ));
+                return;
+            }
+        };
+        if file_size == 0 || file_size > self.max_file_size {
+            self.messages.items.push(MsgItem::info_msg(
+                format!(
+                    "Files must be between 1 B and {}",
+                    transfer::human_size(self.max_file_size)
+                ),
+                Color::Rgb(255, 127, 127),
+            ));
+            return;
+        }
+
+        let data = match tokio::fs::read(&path).await {
+            Ok(data) => data,
+            Err(err) => {
+                self.messages.items.push(MsgItem::info_msg(
+                    format!("Cannot read {}: {}", path.display(), err),
+                    Color::Rgb(255, 127, 127),
+                ));
+                return;
+            }
+        };
+        let user = self.client.user.lock().unwrap().clone();
+        let Some(addr) = user.addr else {
+            return;
+        };
+
+        let file = FileInfo::new(&path, &data, addr, &user.id);
+        if let Err(err) = self
+            .client
+            .send_msg(UserMsg::FileOffer { file: file.clone() })
+            .await
+        {
+            self.messages.items.push(MsgItem::info_msg(
+                "Failed sending message".to_string(),
+                Color::Rgb(255, 127, 127),
+            ));
+            info!("{}", err);
+            return;
         }
-        None
+        self.outgoing = Some(OutgoingFile::new(file, data));
     }
-}
 
-type Command = (Regex, Action);
+    async fn send_file_chunks(&mut self) {
+        let Some(outgoing) = self.outgoing.as_mut() else {
+            return;
+        };
+        let file_id = outgoing.buffer.info.file_id.clone();
+        let chunks = std::iter::from_fn(|| outgoing.next_chunk())
+            .take(CHUNKS_PER_TICK)
+            .collect::<Vec<(u64, String)>>();
+
+        for (index, data) in chunks {
+            let send_result = self
+                .client
+                .send_msg(UserMsg::FileChunk {
+                    file_id: file_id.clone(),
+                    index,
+                    data,
+                })
+                .await;
+            if let Err(err) = send_result {
+                self.outgoing = None;
+                self.messages.items.push(MsgItem::info_msg(
+                    "Failed sending file".to_string(),
+                    Color::Rgb(255, 127, 127),
+                ));
+                info!("{}", err);
+                return;
+            }
+        }
 
-pub enum Action {
-    Ban,
+        if self.outgoing.as_ref().is_some_and(OutgoingFile::is_done) {
+            if let Some(outgoing) = self.outgoing.take() {
+                self.messages.items.push(MsgItem::info_msg(
+                    format!(
+                        "Sent {} ({})",
+                        outgoing.buffer.info.name,
+                        outgoing.buffer.info.human_size()
+                    ),
+                    Color::Rgb(75, 75, 75),
+                ));
+            }
+        }
+    }
+
+    fn receive_file_chunk(&mut self, file_id: &str, index: u64, data: &str) {
+        let Some(incoming) = self.incoming.get_mut(file_id) else {
+            return;
+        };
+        if let Err(err) = incoming.push_chunk(index, data) {
+            if let Some(incoming) = self.incoming.remove(file_id) {
+                incoming.discard();
+            }
+            self.messages.items.push(MsgItem::info_msg(
+                format!("Transfer failed: {}", err),
+                Color::Rgb(255, 127, 127),
+            ));
+            return;
+        }
+        if !incoming.is_complete() {
+            return;
+        }
+
+        let Some(incoming) = self.incoming.remove(file_id) else {
+            return;
+        };
+        let name = incoming.info.name.clone();
+        match incoming.finish() {
+            Ok(path) => self.messages.items.push(MsgItem::info_msg(
+                format!("Saved {} to {}", name, path.display()),
+                Color::Rgb(75, 75, 75),
+            )),
+            Err(err) => self
+                .messages
+                .items
+                .push(MsgItem::info_msg(err, Color::Rgb(255, 127, 127))),
+        }
+    }
+
+    async fn download_file(&mut self, name: &str) {
+        let Some(file) = self
+            .available
+            .iter()
+            .rev()
+            .find(|file| name.is_empty() || file.name == name)
+            .cloned()
+        else {
+            self.messages.items.push(MsgItem::info_msg(
+                "No such shared file".to_string(),
+                Color::Rgb(255, 127, 127),
+            ));
+            return;
+        };
+        if self.incoming.contains_key(&file.file_id) {
+            self.messages.items.push(MsgItem::info_msg(
+                format!("{} is already being downloaded", file.name),
+                Color::Rgb(255, 127, 127),
+            ));
+            return;
+        }
+
+        match IncomingFile::create(file.clone(), &self.download_dir) {
+            Ok(incoming) => {
+                self.incoming.insert(file.file_id.clone(), incoming);
+                self.send_or_report(UserMsg::FileReq {
+                    file_id: file.file_id,
+                })
+                .await;
+            }
+            Err(err) => self.messages.items.push(MsgItem::info_msg(
+                format!("Cannot save {}: {}", file.name, err),
+                Color::Rgb(255, 127, 127),
+            )),
+        }
+    }
+
+    pub fn transfer_status(&self) -> Option<String> {
+        let mut statuses = self
+            .incoming
+            .values()
+            .map(|incoming| format!("receiving {} {}%", incoming.info.name, incoming.progress()))
+            .collect::<Vec<String>>();
+        if let Some(outgoing) = &self.outgoing {
+            statuses.insert(
+                0,
+                format!(
+                    "sending {} {}%",
+                    outgoing.buffer.info.name,
+                    outgoing.progress()
+                ),
+            );
+        }
+
+        (!statuses.is_empty()).then(|| statuses.join(" · "))
+    }
+
+    fn navigate_popup(state: &mut ListState, len: usize, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Up | KeyCode::Char('k') if len > 0 => {
+                let i = state.selected().unwrap_or(0);
+                state.select(Some(i.saturating_sub(1)));
+            }
+            KeyCode::Down | KeyCode::Char('j') if len > 0 => {
+                let i = state.selected().unwrap_or(0);
+                state.select(Some((i + 1).min(len - 1)));
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn new_msgs_status(&self) -> Option<String> {
+        if !self.messages.is_highlighted {
+            return None;
+        }
+        let count = self
+            .messages
+            .items
+            .iter()
+            .skip(self.followed_len)
+            .filter(|item| item.msg.is_some())
+            .count();
+        let hint = self
+            .keymap
+            .binding(KeyAction::ScrollBottom)
+            .map(|binding| format!(" [{}]", binding))
+            .unwrap_or_default();
+        (count > 0).then(|| {
+            format!(
+                "{} new message{} below{}",
+                count,
+                if count == 1 { "" } else { "s" },
+                hint
+            )
+        })
+    }
+
+    pub fn help_hint(&self) -> String {
+        self.keymap
+            .binding(KeyAction::Help)
+            .map_or("/help".to_string(), |binding| format!("[{}] help", binding))
+    }
+
+    pub fn pin_summaries(&self) -> Vec<String> {
+        self.pins
+            .iter()
+            .map(|msg_id| {
+                self.messages
+                    .items
+                    .iter()
+                    .filter_map(|item| item.msg.as_ref())
+                    .find(|msg| msg.msg_id == *msg_id)
+                    .map_or("message not loaded".to_string(), |msg| {
+                        let author = self
+                            .users
+                            .get(&msg.sender_addr)
+                            .map_or(msg.last_username.clone(), |user| user.id.clone());
+                        let first_line = msg.content.lines().next().unwrap_or_default();
+                        format!("{}: {}", author, first_line)
+                    })
+            })
+            .collect()
+    }
+
+    fn handle_pins_input(&mut self, code: KeyCode) -> bool {
+        if Self::navigate_popup(&mut self.pins_state, self.pins.len(), code) {
+            return true;
+        }
+        if code != KeyCode::Enter {
+            return false;
+        }
+
+        let item_pos = self
+            .pins_state
+            .selected()
+            .and_then(|i| self.pins.get(i))
+            .and_then(|msg_id| {
+                self.messages
+                    .items
+                    .iter()
+                    .position(|item| item.msg.as_ref().is_some_and(|msg| msg.msg_id == *msg_id))
+            });
+        if let Some(item_pos) = item_pos {
+            self.messages.is_highlighted = true;
+            self.messages.state.select(Some(item_pos));
+        }
+        self.current_popup = PopupState::None;
+        true
+    }
+
+    async fn handle_search_input(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        if modifiers.contains(KeyModifiers::CONTROL) {
+            return false;
+        }
+        let Some(input) = self.search_input.as_mut() else {
+            return false;
+        };
+        match code {
+            KeyCode::Esc => {
+                self.search_input = None;
+            }
+            KeyCode::Enter => {
+                let query = input.clone();
+                self.search_input = None;
+                if !query.trim().is_empty() {
+                    self.start_search(&query).await;
+                }
+            }
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => {
+                input.push(c);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    async fn start_search(&mut self, query: &str) {
+        let mut search = match Search::new(query) {
+            Ok(search) => search,
+            Err(err) => {
+                self.messages
+                    .items
+                    .push(MsgItem::info_msg(err, Color::Rgb(255, 127, 127)));
+                return;
+            }
+        };
+        search.refresh(&self.messages.items);
+        let pattern = search.pattern.as_str().to_string();
+        let has_hits = !search.hits.is_empty();
+        self.search = Some(search);
+
+        if has_hits {
+            self.messages.is_highlighted = false;
+            self.search_step(true);
+        } else {
+            self.send_or_report(UserMsg::SearchReq { pattern }).await;
+        }
+    }
+
+    fn search_step(&mut self, older: bool) {
+        let from = self
+            .messages
+            .is_highlighted
+            .then(|| self.messages.state.selected())
+            .flatten();
+        if let Some(hit) = self
+            .search
+            .as_mut()
+            .and_then(|search| search.step(from, older))
+        {
+            self.messages.is_highlighted = true;
+            self.messages.state.select(Some(hit));
+        }
+    }
+
+    fn show_search_results(&mut self, pattern: String, mut messages: Vec<TextMessage>) {
+        let Some(query) = self
+            .search
+            .as_ref()
+            .filter(|search| search.pattern.as_str() == pattern)
+            .map(|search| search.query.clone())
+        else {
+            return;
+        };
+        messages.retain(|msg| {
+            !self.ignored.contains_key(&msg.sender_uid)
+                && !self.messages.items.iter().any(|item| {
+                    item.msg
+                        .as_ref()
+                        .is_some_and(|loaded| loaded.msg_id == msg.msg_id)
+                })
+        });
+        messages.sort_by_key(|msg| msg.timestamp);
+
+        let count = messages.len();
+        for msg in messages {
+            let (id, color) = if let Some(user) = self.users.get(&msg.sender_addr) {
+                (user.id.clone(), user.color.clone())
+            } else {
+                (msg.last_username.clone(), msg.last_color.clone())
+            };
+            let pos = self
+                .messages
+                .items
+                .iter()
+                .position(|item| {
+                    item.msg
+                        .as_ref()
+                        .is_some_and(|loaded| loaded.timestamp > msg.timestamp)
+                })
+                .unwrap_or(self.messages.items.len());
+            let item = self.user_msg_item(&msg, id, color);
+            self.insert_msg_item(pos, item);
+        }
+
+        if count == 0 {
+            self.messages.items.push(MsgItem::info_msg(
+                format!("No messages match {}", query),
+                Color::Rgb(75, 75, 75),
+            ));
+            return;
+        }
+        self.messages.items.push(MsgItem::info_msg(
+            format!(
+                "Loaded {} older match{} for {}",
+                count,
+                if count == 1 { "" } else { "es" },
+                query
+            ),
+            Color::Rgb(75, 75, 75),
+        ));
+        if let Some(search) = self.search.as_mut() {
+            search.refresh(&self.messages.items);
+        }
+        self.messages.is_highlighted = false;
+        self.search_step(true);
+    }
+
+    pub fn search_bar(&self) -> Option<String> {
+        if let Some(input) = &self.search_input {
+            return Some(format!("search: {}_", input));
+        }
+        self.search
+            .as_ref()
+            .map(|search| format!("{} [esc] close", search.status()))
+    }
+
+    fn handle_emoji_input(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        if modifiers.contains(KeyModifiers::CONTROL) {
+            return false;
+        }
+        let results = emoji::search(&self.emoji_query);
+        match code {
+            KeyCode::Up | KeyCode::Down => {
+                Self::navigate_popup(&mut self.emoji_state, results.len(), code);
+            }
+            KeyCode::Char(c) => {
+                self.emoji_query.push(c);
+                self.emoji_state.select(Some(0));
+            }
+            KeyCode::Backspace => {
+                self.emoji_query.pop();
+                self.emoji_state.select(Some(0));
+            }
+            KeyCode::Enter => {
+                if let Some((_, emoji)) = self.emoji_state.selected().and_then(|i| results.get(i)) {
+                    self.msg_area.insert_emoji(emoji);
+                }
+                self.current_popup = PopupState::None;
+            }
+            KeyCode::Esc => self.current_popup = PopupState::None,
+            _ => return false,
+        }
+        true
+    }
+
+    fn handle_mentions_input(&mut self, code: KeyCode) -> bool {
+        if Self::navigate_popup(&mut self.mentions_state, self.mentions.len(), code) {
+            return true;
+        }
+        if code != KeyCode::Enter {
+            return false;
+        }
+
+        if let Some((item_pos, _)) = self
+            .mentions_state
+            .selected()
+            .and_then(|i| self.mentions.get(i))
+        {
+            self.messages.is_highlighted = true;
+            self.messages.state.select(Some(*item_pos));
+        }
+        self.current_popup = PopupState::None;
+        true
+    }
+
+    fn load_input_history(&mut self) {
+        let room_id = self.client.room.lock().unwrap()._id.clone();
+        let history = match self
+            .db
+            .lock()
+            .unwrap()
+            .input_history
+            .find_one(doc! {"_id": room_id})
+        {
+            Ok(history) => history,
+            Err(err) => {
+                warn!("{}", err);
+                None
+            }
+        };
+        self.history = history.map(|history| history.entries).unwrap_or_default();
+        self.history_pos = None;
+    }
+
+    fn record_input(&mut self, text: &str) {
+        self.history_pos = None;
+        if self.history.last().is_some_and(|last| last == text) {
+            return;
+        }
+        self.history.push(text.to_string());
+        if self.history.len() > MAX_INPUT_HISTORY {
+            self.history.remove(0);
+        }
+
+        let room_id = self.client.room.lock().unwrap()._id.clone();
+        let db = self.db.lock().unwrap();
+        let result = db
+            .input_history
+            .update_one(
+                doc! {"_id": &room_id},
+                doc! {"$set": doc! {"entries": self.history.clone()}},
+            )
+            .and_then(|update_result| {
+                if update_result.matched_count == 0 {
+                    db.input_history.insert_one(InputHistory {
+                        _id: room_id,
+                        entries: self.history.clone(),
+                    })?;
+                }
+                Ok(())
+            });
+        if let Err(err) = result {
+            warn!("{}", err);
+        }
+    }
+
+    fn recall_history(&mut self, older: bool) {
+        let pos = match (self.history_pos, older) {
+            (None, true) if !self.history.is_empty() => {
+                self.history_draft = self.msg_area.text();
+                Some(self.history.len() - 1)
+            }
+            (Some(pos), true) => Some(pos.saturating_sub(1)),
+            (Some(pos), false) if pos + 1 < self.history.len() => Some(pos + 1),
+            (Some(_), false) => None,
+            _ => return,
+        };
+
+        self.history_pos = pos;
+        let text = pos.map_or(self.history_draft.clone(), |pos| self.history[pos].clone());
+        self.msg_area.set_text(&text);
+    }
+
+    fn insert_unread_divider(&mut self) {
+        let room_id = self.client.room.lock().unwrap()._id.clone();
+        let marker = match self
+            .db
+            .lock()
+            .unwrap()
+            .read_markers
+            .find_one(doc! {"_id": room_id})
+        {
+            Ok(marker) => marker,
+            Err(err) => {
+                warn!("{}", err);
+                None
+            }
+        };
+
+        if let Some(marker) = marker {
+            let read_pos = self.messages.items.iter().position(|item| {
+                item.msg
+                    .as_ref()
+                    .is_some_and(|msg| msg.msg_id == marker.msg_id)
+            });
+            if let Some(read_pos) = read_pos {
+                if read_pos + 1 < self.messages.items.len() {
+                    self.insert_msg_item(
+                        read_pos + 1,
+                        MsgItem::info_msg(
+                            "──── new messages ────".to_string(),
+                            Color::Rgb(255, 127, 127),
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    async fn mark_read(&mut self) {
+        let last_msg_id = self
+            .messages
+            .items
+            .iter()
+            .rev()
+            .find_map(|item| item.msg.as_ref().map(|msg| msg.msg_id.clone()));
+        if last_msg_id.is_none() || last_msg_id == self.last_read_id {
+            return;
+        }
+
+        self.last_read_id = last_msg_id.clone();
+        self.save_read_marker();
+        if self.read_receipts {
+            if let Some(msg_id) = last_msg_id {
+                if let Err(err) = self.client.send_msg(UserMsg::Read { msg_id }).await {
+                    info!("{}", err);
+                }
+            }
+        }
+    }
+
+    fn save_read_marker(&self) {
+        let Some(msg_id) = self.last_read_id.clone() else {
+            return;
+        };
+        let room_id = self.client.room.lock().unwrap()._id.clone();
+        let db = self.db.lock().unwrap();
+
+        let result = db
+            .read_markers
+            .update_one(
+                doc! {"_id": &room_id},
+                doc! {"$set": doc! {"msg_id": &msg_id}},
+            )
+            .and_then(|update_result| {
+                if update_result.matched_count == 0 {
+                    db.read_markers.insert_one(ReadMarker {
+                        _id: room_id,
+                        msg_id,
+                    })?;
+                }
+                Ok(())
+            });
+        if let Err(err) = result {
+            warn!("{}", err);
+        }
+    }
+
+    fn apply_read_receipts(&mut self) {
+        let own_addr = self.client.user.lock().unwrap().addr;
+        let target_user = self.client.user.lock().unwrap().clone();
+
+        let mut seen_by = HashMap::<usize, Vec<String>>::new();
+        for (addr, msg_id) in self.receipts.iter() {
+            let Some(reader) = self.users.get(addr).map(|user| user.id.clone()) else {
+                continue;
+            };
+            let read_pos = self
+                .messages
+                .items
+                .iter()
+                .position(|item| item.msg.as_ref().is_some_and(|msg| msg.msg_id == *msg_id));
+            let seen_pos = read_pos.and_then(|read_pos| {
+                self.messages.items[..=read_pos].iter().rposition(|item| {
+                    item.msg
+                        .as_ref()
+                        .is_some_and(|msg| Some(msg.sender_addr) == own_addr)
+                })
+            });
+            if let Some(seen_pos) = seen_pos {
+                seen_by.entry(seen_pos).or_default().push(reader);
+            }
+        }
+
+        for (i, item) in self.messages.items.iter_mut().enumerate() {
+            let mut readers = seen_by.remove(&i).unwrap_or_default();
+            readers.sort();
+            if item.seen_by != readers {
+                item.seen_by = readers;
+                item.rerender(&self.style, &target_user);
+            }
+        }
+    }
+
+    fn handle_deleting_chars(&mut self) {
+        if self.msg_area.textarea.cursor().1 == 0 && self.msg_area.textarea.cursor().0 > 0 {
+            self.msg_area.textarea.delete_newline();
+        } else {
+            self.msg_area.textarea.delete_char();
+        }
+    }
+
+    async fn run_command(&mut self, command: &Command, args: Vec<String>) {
+        if command.role == Role::Owner && !self.is_owner {
+            self.messages.items.push(MsgItem::info_msg(
+                format!("/{} can only be used by the room owner", command.name),
+                Color::Rgb(255, 127, 127),
+            ));
+            return;
+        }
+
+        match command.action {
+            Action::Ban => {
+                if let Some(user_addr) = self.find_user_addr(&args[0]) {
+                    self.client
+                        .ban(&user_addr)
+                        .await
+                        .unwrap_or_else(|err| warn!("{}", err));
+                }
+            }
+            Action::Msg => {
+                if let Some(user_addr) = self.find_user_addr(&args[0]) {
+                    self.send_private_msg(user_addr, &args[1]).await;
+                }
+            }
+            Action::Nick => {
+                if !is_valid_username(&args[0]) {
+                    self.messages.items.push(MsgItem::info_msg(
+                        format!(
+                            "Invalid nickname: {}, use up to {} characters without spaces",
+                            args[0], MAX_USERNAME_LEN
+                        ),
+                        Color::Rgb(255, 127, 127),
+                    ));
+                    return;
+                }
+                let mut user = self.client.user.lock().unwrap().clone();
+                user.id = args[0].clone();
+                self.send_user_update(user).await;
+            }
+            Action::Color => {
+                if let Ok(color) = UserColor::from_str(&args[0]) {
+                    let mut user = self.client.user.lock().unwrap().clone();
+                    user.color = color;
+                    self.send_user_update(user).await;
+                } else {
+                    self.messages.items.push(MsgItem::info_msg(
+                        format!("No such color: {}", args[0]),
+                        Color::Rgb(255, 127, 127),
+                    ));
+                }
+            }
+            Action::Edit => {
+                let own_uid = self.client.user.lock().unwrap().uid.clone();
+                match self.target_msg() {
+                    Some(msg) if !own_uid.is_empty() && msg.sender_uid == own_uid => {
+                        self.send_or_report(UserMsg::Edit {
+                            msg_id: msg.msg_id,
+                            content: args[0].clone(),
+                        })
+                        .await;
+                    }
+                    _ => {
+                        self.messages.items.push(MsgItem::info_msg(
+                            "You can only edit your own messages".to_string(),
+                            Color::Rgb(255, 127, 127),
+                        ));
+                    }
+                }
+            }
+            Action::Delete => {
+                if let Some(msg) = self.target_msg() {
+                    self.send_or_report(UserMsg::Delete { msg_id: msg.msg_id })
+                        .await;
+                } else {
+                    self.messages.items.push(MsgItem::info_msg(
+                        "No message to delete".to_string(),
+                        Color::Rgb(255, 127, 127),
+                    ));
+                }
+            }
+            Action::Search => {
+                if args[0].is_empty() {
+                    self.search_input = Some(String::new());
+                } else {
+                    self.start_search(&args[0]).await;
+                }
+            }
+            Action::Compose => {
+                self.compose = !self.compose;
+                self.msg_area
+                    .set_compose(self.compose, self.keymap.binding(KeyAction::Send));
+            }
+            Action::Help => {
+                self.current_popup = PopupState::Help;
+            }
+            Action::Raw => {
+                self.style.markdown = !self.style.markdown;
+                let result = self.db.lock().unwrap().local_data.update_one(
+                    doc! {},
+                    doc! {"$set": doc! {
+                        "markdown": self.style.markdown
+                    }},
+                );
+                if let Err(err) = result {
+                    warn!("{}", err);
+                }
+
+                let target_user = self.client.user.lock().unwrap().clone();
+                self.messages
+                    .items
+                    .iter_mut()
+                    .for_each(|item| item.rerender(&self.style, &target_user));
+            }
+            Action::Send => {
+                self.send_file(args[0].trim()).await;
+            }
+            Action::Accept | Action::Fetch => {
+                self.download_file(args[0].trim()).await;
+            }
+            Action::Decline => {
+                let name = args[0].trim();
+                if let Some(pos) = self
+                    .available
+                    .iter()
+                    .rposition(|file| name.is_empty() || file.name == name)
+                {
+                    let file = self.available.remove(pos);
+                    self.messages.items.push(MsgItem::info_msg(
+                        format!("Declined {}", file.name),
+                        Color::Rgb(75, 75, 75),
+                    ));
+                } else {
+                    self.messages.items.push(MsgItem::info_msg(
+                        "No such shared file".to_string(),
+                        Color::Rgb(255, 127, 127),
+                    ));
+                }
+            }
+            Action::Pin => {
+                if let Some(msg) = self.highlighted_or_last_msg() {
+                    self.send_or_report(UserMsg::Pin { msg_id: msg.msg_id })
+                        .await;
+                } else {
+                    self.messages.items.push(MsgItem::info_msg(
+                        "No message to pin".to_string(),
+                        Color::Rgb(255, 127, 127),
+                    ));
+                }
+            }
+            Action::React => {
+                let reaction = emoji::find(args[0].trim_matches(':')).unwrap_or(&args[0]);
+                if !emoji::is_emoji(reaction) {
+                    self.messages.items.push(MsgItem::info_msg(
+                        format!("Unknown emoji: {}", args[0]),
+                        Color::Rgb(255, 127, 127),
+                    ));
+                } else if let Some(msg) = self.highlighted_or_last_msg() {
+                    self.send_or_report(UserMsg::React {
+                        msg_id: msg.msg_id,
+                        emoji: reaction.to_string(),
+                    })
+                    .await;
+                } else {
+                    self.messages.items.push(MsgItem::info_msg(
+                        "No message to react to".to_string(),
+                        Color::Rgb(255, 127, 127),
+                    ));
+                }
+            }
+            Action::Away | Action::DoNotDisturb | Action::Back => {
+                let presence = match command.action {
+                    Action::Away => Presence::Away,
+                    Action::DoNotDisturb => Presence::DoNotDisturb,
+                    _ => Presence::Online,
+                };
+                let status = args
+                    .first()
+                    .map(|status| status.trim().to_string())
+                    .filter(|status| !status.is_empty());
+                self.auto_away = false;
+                self.send_presence(presence, status).await;
+            }
+            Action::Topic => {
+                let topic = Some(args[0].trim().to_string()).filter(|t| !t.is_empty());
+                self.send_or_report(UserMsg::Topic { topic }).await;
+            }
+            Action::Motd => {
+                let motd = Some(args[0].trim().to_string()).filter(|m| !m.is_empty());
+                self.send_or_report(UserMsg::Motd { motd }).await;
+            }
+            Action::Ignore => {
+                let Some(user_addr) = self.find_user_addr(&args[0]) else {
+                    return;
+                };
+                let user = self.users[&user_addr].clone();
+                let info = if self.ignored.contains_key(&user.uid) {
+                    format!("{} (user id {}) is already ignored", user.id, user.uid)
+                } else {
+                    self.set_ignored(&user.uid, &user.id, true);
+                    self.typing_users.remove(&user_addr);
+                    format!("{} (user id {}) is now ignored", user.id, user.uid)
+                };
+                self.messages
+                    .items
+                    .push(MsgItem::info_msg(info, Color::Rgb(75, 75, 75)));
+            }
+            Action::Unignore => {
+                let name = args[0].to_lowercase();
+                let ignored_uid = self
+                    .ignored
+                    .iter()
+                    .find(|(uid, username)| **uid == args[0] || username.to_lowercase() == name)
+                    .map(|(uid, _)| uid.clone())
+                    .or_else(|| {
+                        self.users
+                            .values()
+                            .find(|user| {
+                                user.id.to_lowercase() == name
+                                    && self.ignored.contains_key(&user.uid)
+                            })
+                            .map(|user| user.uid.clone())
+                    });
+                if let Some(uid) = ignored_uid {
+                    self.set_ignored(&uid, &args[0], false);
+                    self.messages.items.push(MsgItem::info_msg(
+                        format!("{} is no longer ignored", args[0]),
+                        Color::Rgb(75, 75, 75),
+                    ));
+                } else {
+                    self.messages.items.push(MsgItem::info_msg(
+                        format!("{} is not ignored", args[0]),
+                        Color::Rgb(255, 127, 127),
+                    ));
+                }
+            }
+            Action::Reply => {
+                if let Some(user_addr) = self.last_whisper_addr {
+                    self.send_private_msg(user_addr, &args[0]).await;
+                } else {
+                    self.messages.items.push(MsgItem::info_msg(
+                        "No whisper to reply to".to_string(),
+                        Color::Rgb(255, 127, 127),
+                    ));
+                }
+            }
+        }
+    }
+
+    async fn send_private_msg(&mut self, recipient: SocketAddr, text: &str) {
+        if !self.users.contains_key(&recipient) {
+            self.messages.items.push(MsgItem::info_msg(
+                "User has left".to_string(),
+                Color::Rgb(255, 127, 127),
+            ));
+            return;
+        }
+
+        let msg = TextMessage::new(
+            &self.client.user.lock().unwrap(),
+            &self.client.room.lock().unwrap()._id,
+            text,
+        );
+        // The whisper is shown once the server echoes it back as delivered
+        if let Err(err) = self
+            .client
+            .send_msg(UserMsg::Private { msg, recipient })
+            .await
+        {
+            self.messages.items.push(MsgItem::info_msg(
+                "Failed sending message".to_string(),
+                Color::Rgb(255, 127, 127),
+            ));
+            info!("{}", err);
+        }
+    }
+
+    fn complete(&mut self, forward: bool) {
+        let (row, col) = self.msg_area.textarea.cursor();
+        let replaced_len = match self.completion.as_mut() {
+            Some(completion) if completion.row == row && completion.end() == col => {
+                let replaced_len = completion.current().chars().count();
+                completion.cycle(forward);
+                replaced_len
+            }
+            _ => {
+                let before_cursor = self.msg_area.textarea.lines()[row]
+                    .chars()
+                    .take(col)
+                    .collect::<String>();
+                let mut users = self
+                    .users
+                    .values()
+                    .map(|user| user.id.clone())
+                    .collect::<Vec<String>>();
+                users.sort();
+                users.dedup();
+                let Some((start, candidates)) =
+                    completion::complete(&before_cursor, &users, &self.command_names())
+                else {
+                    return;
+                };
+                let completion = self.completion.insert(Completion {
+                    row,
+                    start,
+                    candidates,
+                    index: 0,
+                });
+                col - completion.start
+            }
+        };
+
+        let Some(completion) = self.completion.as_ref() else {
+            return;
+        };
+        for _ in 0..replaced_len {
+            self.msg_area.textarea.delete_char();
+        }
+        self.msg_area.textarea.insert_str(completion.current());
+        if completion.candidates.len() > 1 {
+            self.current_popup = PopupState::Completion;
+        }
+    }
+
+    fn command_names(&self) -> Vec<(String, bool)> {
+        commands::COMMANDS
+            .iter()
+            .flat_map(|command| {
+                std::iter::once(&command.name)
+                    .chain(command.aliases)
+                    .map(|name| (name.to_string(), command.takes_username()))
+            })
+            .collect()
+    }
+
+    fn find_user_addr(&mut self, name: &str) -> Option<SocketAddr> {
+        if let Ok(addr) = SocketAddr::from_str(name) {
+            if self.users.contains_key(&addr) {
+                return Some(addr);
+            }
+        }
+
+        let matches = self
+            .users
+            .iter()
+            .filter(|(_, user)| user.id.to_lowercase() == name.to_lowercase())
+            .map(|(addr, _)| *addr)
+            .collect::<Vec<SocketAddr>>();
+
+        match matches.as_slice() {
+            [addr] => Some(*addr),
+            [] => {
+                self.messages.items.push(MsgItem::info_msg(
+                    format!("No such user: {}", name),
+                    Color::Rgb(255, 127, 127),
+                ));
+                None
+            }
+            addrs => {
+                self.messages.items.push(MsgItem::info_msg(
+                    format!(
+                        "Ambiguous username {}, use one of: {}",
+                        name,
+                        addrs
+                            .iter()
+                            .map(|addr| addr.to_string())
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    ),
+                    Color::Rgb(255, 127, 127),
+                ));
+                None
+            }
+        }
+    }
+
+    async fn send_user_update(&mut self, user: User) {
+        self.send_or_report(UserMsg::UserUpdate { user }).await;
+    }
+
+    async fn send_presence(&mut self, presence: Presence, status: Option<String>) {
+        self.send_or_report(UserMsg::Presence { presence, status })
+            .await;
+    }
+
+    async fn update_presence(&mut self) {
+        let Some(away_after) = self.away_after else {
+            return;
+        };
+        let is_online = self.client.user.lock().unwrap().presence == Presence::Online;
+        if is_online && !self.auto_away && self.last_activity_at.elapsed() >= away_after {
+            self.auto_away = true;
+            self.send_presence(Presence::Away, Some("idle".to_string()))
+                .await;
+        }
+    }
+
+    async fn send_or_report(&mut self, msg: UserMsg) {
+        if let Err(err) = self.client.send_msg(msg).await {
+            self.messages.items.push(MsgItem::info_msg(
+                "Failed sending message".to_string(),
+                Color::Rgb(255, 127, 127),
+            ));
+            info!("{}", err);
+        }
+    }
+
+    pub fn room_title(&self) -> String {
+        let room_id = self.client.room.lock().unwrap()._id.clone();
+        match &self.topic {
+            Some(topic) => format!("{} — {}", room_id, topic),
+            None => room_id,
+        }
+    }
+
+    pub fn typing_status(&self) -> Option<String> {
+        let mut names = self
+            .typing_users
+            .keys()
+            .filter_map(|addr| self.users.get(addr))
+            .map(|user| user.id.clone())
+            .collect::<Vec<String>>();
+        names.sort();
+
+        match names.as_slice() {
+            [] => None,
+            [name] => Some(format!("{} is typing…", name)),
+            [first, second] => Some(format!("{} and {} are typing…", first, second)),
+            _ => Some("several people are typing…".to_string()),
+        }
+    }
+
+    async fn notify_typing(&mut self) {
+        self.last_keystroke_at = Some(Instant::now());
+        if self
+            .typing_sent_at
+            .is_none_or(|sent_at| sent_at.elapsed() >= TYPING_REFRESH)
+        {
+            self.typing_sent_at = Some(Instant::now());
+            if let Err(err) = self
+                .client
+                .send_msg(UserMsg::Typing { is_typing: true })
+                .await
+            {
+                info!("{}", err);
+            }
+        }
+    }
+
+    async fn stop_typing(&mut self) {
+        if self.typing_sent_at.take().is_some() {
+            if let Err(err) = self
+                .client
+                .send_msg(UserMsg::Typing { is_typing: false })
+                .await
+            {
+                info!("{}", err);
+            }
+        }
+    }
+
+    async fn update_typing(&mut self) {
+        let is_idle = self
+            .last_keystroke_at
+            .is_none_or(|keystroke_at| keystroke_at.elapsed() >= TYPING_TIMEOUT);
+        if self.typing_sent_at.is_some() && (is_idle || self.msg_area.is_empty()) {
+            self.stop_typing().await;
+        }
+        self.typing_users
+            .retain(|_, typing_since| typing_since.elapsed() < TYPING_TIMEOUT);
+    }
+
+    fn user_msg_item(&self, msg: &TextMessage, user_id: String, color: UserColor) -> MsgItem<'a> {
+        let quote = msg.in_reply_to.as_ref().and_then(|parent_id| {
+            self.messages
+                .items
+                .iter()
+                .rev()
+                .find(|item| {
+                    item.msg
+                        .as_ref()
+                        .is_some_and(|msg| msg.msg_id == *parent_id)
+                })
+                .and_then(|item| item.quote())
+        });
+        MsgItem::user_msg(
+            msg,
+            user_id,
+            color,
+            quote,
+            &self.style,
+            &self.client.user.lock().unwrap(),
+        )
+    }
+
+    fn cancel_reply(&mut self) {
+        self.reply_to = None;
+        self.msg_area.set_title(None);
+    }
+
+    fn update_msg_item(&mut self, msg_id: &str, update: impl FnOnce(&mut TextMessage)) {
+        let target_user = self.client.user.lock().unwrap().clone();
+        if let Some(item) = self
+            .messages
+            .items
+            .iter_mut()
+            .rev()
+            .find(|item| item.msg.as_ref().is_some_and(|msg| msg.msg_id == msg_id))
+        {
+            if let Some(msg) = item.msg.as_mut() {
+                update(msg);
+            }
+            item.rerender(&self.style, &target_user);
+        }
+    }
+
+    fn highlighted_msg(&self) -> Option<TextMessage> {
+        if !self.messages.is_highlighted {
+            return None;
+        }
+        self.messages
+            .state
+            .selected()
+            .and_then(|i| self.messages.items.get(i))
+            .and_then(|item| item.msg.clone())
+    }
+
+    fn highlighted_or_last_msg(&self) -> Option<TextMessage> {
+        self.highlighted_msg()
+            .or(self.selected_msg.clone())
+            .or_else(|| {
+                self.messages
+                    .items
+                    .iter()
+                    .rev()
+                    .filter_map(|item| item.msg.clone())
+                    .find(|msg| !msg.deleted)
+            })
+    }
+
+    fn target_msg(&self) -> Option<TextMessage> {
+        if let Some(msg) = self.highlighted_msg().or(self.selected_msg.clone()) {
+            return Some(msg);
+        }
+        let own_uid = self.client.user.lock().unwrap().uid.clone();
+        self.messages
+            .items
+            .iter()
+            .rev()
+            .filter_map(|item| item.msg.as_ref())
+            .find(|msg| msg.sender_uid == own_uid && !msg.deleted)
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ChatApp;
+    use crate::{
+        db::DbRepo,
+        network::{client::ChatClient, Presence, User},
+        schema::{Color, RoomHeader, TextMessage},
+        tui::{
+            search::Search,
+            ui::{PopupState, Tui},
+        },
+    };
+    use crossterm::event::{KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+    use ratatui::{backend::TestBackend, Terminal};
+    use std::{
+        net::SocketAddr,
+        path::Path,
+        str::FromStr,
+        sync::{Arc, Mutex},
+        time::{Duration, SystemTime},
+    };
+
+    fn room_header(room_id: &str) -> RoomHeader {
+        RoomHeader {
+            _id: room_id.into(),
+            addr: SocketAddr::from_str("127.0.0.1:12345").unwrap(),
+            passwd: None,
+        }
+    }
+
+    fn chat_app(db_path: &Path) -> ChatApp<'static> {
+        let user = User {
+            id: "user1".into(),
+            uid: "uid1".into(),
+            addr: None,
+            color: Color::White,
+            presence: Presence::Online,
+            status: None,
+        };
+        let db = Arc::new(Mutex::new(DbRepo::new(db_path).unwrap()));
+        ChatApp::new(
+            ChatClient::new(room_header("someroom"), user),
+            false,
+            db,
+            false,
+        )
+    }
+
+    #[test]
+    fn input_history() {
+        let db_path = Path::new("db_input_history");
+        let mut app = chat_app(db_path);
+
+        app.recall_history(true);
+        assert_eq!(app.history_pos, None);
+        assert_eq!(app.msg_area.text(), "");
+
+        app.record_input("first");
+        app.record_input("second");
+        app.record_input("second");
+        app.record_input("third");
+        assert_eq!(app.history, vec!["first", "second", "third"]);
+
+        app.msg_area.set_text("draft");
+        app.recall_history(true);
+        assert_eq!(app.msg_area.text(), "third");
+        app.recall_history(true);
+        app.recall_history(true);
+        assert_eq!(app.msg_area.text(), "first");
+        app.recall_history(true);
+        assert_eq!(app.history_pos, Some(0));
+        assert_eq!(app.msg_area.text(), "first");
+
+        app.recall_history(false);
+        assert_eq!(app.msg_area.text(), "second");
+        app.recall_history(false);
+        app.recall_history(false);
+        assert_eq!(app.history_pos, None);
+        assert_eq!(app.msg_area.text(), "draft");
+        app.recall_history(false);
+        assert_eq!(app.msg_area.text(), "draft");
+
+        *app.client.room.lock().unwrap() = room_header("otherroom");
+        app.load_input_history();
+        assert!(app.history.is_empty());
+        app.record_input("elsewhere");
+
+        *app.client.room.lock().unwrap() = room_header("someroom");
+        app.load_input_history();
+        assert_eq!(app.history, vec!["first", "second", "third"]);
+        *app.client.room.lock().unwrap() = room_header("otherroom");
+        app.load_input_history();
+        assert_eq!(app.history, vec!["elsewhere"]);
+
+        drop(app);
+        std::fs::remove_dir_all(db_path).unwrap();
+    }
+
+    #[test]
+    fn user_list_hit_testing() {
+        let db_path = Path::new("db_user_list");
+        let mut app = chat_app(db_path);
+        let own_addr = SocketAddr::from_str("127.0.0.1:30000").unwrap();
+        app.client.user.lock().unwrap().addr = Some(own_addr);
+        for (n, id) in [
+            "dave", "Bob", "carol", "alice", "eve", "frank", "grace", "heidi", "ivan",
+        ]
+        .iter()
+        .enumerate()
+        {
+            let addr = SocketAddr::from_str(&format!("127.0.0.1:{}", 30000 + n)).unwrap();
+            app.users.insert(
+                addr,
+                User {
+                    id: id.to_string(),
+                    uid: id.to_string(),
+                    addr: Some(addr),
+                    color: Color::White,
+                    presence: Presence::Online,
+                    status: Some("a status long enough to not fit in the list".into()),
+                },
+            );
+        }
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 40)).unwrap();
+        app.current_popup = PopupState::List;
+        terminal
+            .draw(|frame| Tui::<TestBackend>::render(&mut app, frame))
+            .unwrap();
+        let area = app.user_list_area.unwrap();
+        let row_text = |row: u16| {
+            (area.x..area.x + area.width)
+                .map(|x| terminal.backend().buffer().get(x, row).symbol().to_string())
+                .collect::<String>()
+        };
+
+        let user_at =
+            |app: &ChatApp, row: u16| app.user_at(area.x, row).map(|user| user.id.clone());
+        assert_eq!(user_at(&app, area.y), Some("alice".into()));
+        assert_eq!(user_at(&app, area.y + 1), Some("Bob".into()));
+        assert_eq!(user_at(&app, area.y + 7), Some("heidi".into()));
+        for row in 0..8 {
+            let id = user_at(&app, area.y + row).unwrap();
+            assert!(row_text(area.y + row).contains(&id));
+        }
+        assert!(row_text(area.y + 8).starts_with("..."));
+        assert_eq!(user_at(&app, area.y + 8), None);
+        assert_eq!(app.user_at(area.x + area.width, area.y), None);
+        assert_eq!(app.user_at(area.x, area.y.saturating_sub(1)), None);
+
+        app.users
+            .remove(&SocketAddr::from_str("127.0.0.1:30003").unwrap());
+        assert_eq!(user_at(&app, area.y), None);
+        app.handle_mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column: area.x + 2,
+            row: area.y + 2,
+            modifiers: KeyModifiers::NONE,
+        });
+        assert_eq!(app.current_popup, PopupState::None);
+        assert_eq!(app.msg_area.text(), "@carol ");
+
+        drop(app);
+        std::fs::remove_dir_all(db_path).unwrap();
+    }
+
+    #[test]
+    fn search_hits() {
+        let db_path = Path::new("db_search_hits");
+        let mut app = chat_app(db_path);
+        let mut user = app.client.user.lock().unwrap().clone();
+        user.addr = Some(SocketAddr::from_str("127.0.0.1:30000").unwrap());
+        *app.client.user.lock().unwrap() = user.clone();
+
+        let first = TextMessage::new(&user, "someroom", "hello world");
+        let second = TextMessage::new(&user, "someroom", "bye");
+        for msg in [&first, &second] {
+            let item = app.user_msg_item(msg, user.id.clone(), user.color.clone());
+            app.messages.items.push(item);
+        }
+        let mut search = Search::new("hello").unwrap();
+        search.refresh(&app.messages.items);
+        assert_eq!(search.hits, vec![0]);
+        search.step(None, true);
+
+        app.update_msg_item(&first.msg_id, |msg| msg.content = "goodbye".into());
+        app.update_msg_item(&second.msg_id, |msg| msg.content = "hello again".into());
+        search.refresh(&app.messages.items);
+        assert_eq!(search.hits, vec![1]);
+        assert_eq!(search.current, None);
+
+        app.update_msg_item(&second.msg_id, |msg| msg.deleted = true);
+        search.refresh(&app.messages.items);
+        assert!(search.hits.is_empty());
+
+        let pattern = search.pattern.as_str().to_string();
+        app.search = Some(search);
+        app.mentions = vec![(1, "user1: bye".into())];
+        app.hidden_msgs = Some((1, 1));
+        let older = TextMessage {
+            content: "hello from the past".into(),
+            timestamp: Some(SystemTime::now() - Duration::from_secs(3600)),
+            ..TextMessage::new(&user, "someroom", "")
+        };
+        app.show_search_results(pattern.clone(), vec![older.clone(), first.clone()]);
+        assert_eq!(app.messages.items.len(), 4);
+        assert_eq!(
+            app.messages.items[0].msg.as_ref().unwrap().msg_id,
+            older.msg_id
+        );
+        assert_eq!(app.search.as_ref().unwrap().hits, vec![0]);
+        assert!(app.messages.is_highlighted);
+        assert_eq!(app.messages.state.selected(), Some(0));
+        assert_eq!(app.mentions[0].0, 2);
+        assert_eq!(
+            app.messages.items[2].msg.as_ref().unwrap().msg_id,
+            second.msg_id
+        );
+        assert_eq!(app.hidden_msgs, Some((2, 1)));
+
+        app.messages.state.select(Some(2));
+        let item = app.user_msg_item(&older, user.id.clone(), user.color.clone());
+        app.insert_msg_item(0, item);
+        assert_eq!(app.messages.state.selected(), Some(3));
+        assert_eq!(app.mentions[0].0, 3);
+        app.messages.items.remove(0);
+        app.messages.state.select(Some(0));
+
+        app.show_search_results("other".into(), vec![older]);
+        assert_eq!(app.messages.items.len(), 4);
+
+        drop(app);
+        std::fs::remove_dir_all(db_path).unwrap();
+    }
 }
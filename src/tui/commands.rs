@@ -0,0 +1,467 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Ban,
+    Msg,
+    Reply,
+    Nick,
+    Color,
+    Edit,
+    Delete,
+    React,
+    Away,
+    DoNotDisturb,
+    Back,
+    Ignore,
+    Unignore,
+    Topic,
+    Motd,
+    Pin,
+    Send,
+    Accept,
+    Decline,
+    Fetch,
+    Raw,
+    Search,
+    Compose,
+    Help,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Member,
+    Owner,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    User,
+    Word,
+    Text,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arg {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub required: bool,
+}
+
+impl Arg {
+    const fn required(name: &'static str, kind: ArgKind) -> Self {
+        Self {
+            name,
+            kind,
+            required: true,
+        }
+    }
+
+    const fn optional(name: &'static str, kind: ArgKind) -> Self {
+        Self {
+            name,
+            kind,
+            required: false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub args: &'static [Arg],
+    pub role: Role,
+    pub help: &'static str,
+    pub action: Action,
+}
+
+impl Command {
+    pub fn usage(&self) -> String {
+        let mut usage = format!("/{}", self.name);
+        for arg in self.args {
+            if arg.required {
+                usage.push_str(&format!(" <{}>", arg.name));
+            } else {
+                usage.push_str(&format!(" [{}]", arg.name));
+            }
+        }
+        usage
+    }
+
+    pub fn help_line(&self) -> String {
+        let aliases = self
+            .aliases
+            .iter()
+            .map(|alias| format!(", /{}", alias))
+            .collect::<String>();
+        format!("[{}{}] {}", self.usage(), aliases, self.help)
+    }
+
+    pub fn takes_username(&self) -> bool {
+        self.args
+            .first()
+            .is_some_and(|arg| arg.kind == ArgKind::User)
+    }
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "msg",
+        aliases: &["w"],
+        args: &[
+            Arg::required("username|address", ArgKind::User),
+            Arg::required("text", ArgKind::Text),
+        ],
+        role: Role::Member,
+        help: "whisper",
+        action: Action::Msg,
+    },
+    Command {
+        name: "r",
+        aliases: &[],
+        args: &[Arg::required("text", ArgKind::Text)],
+        role: Role::Member,
+        help: "reply to whisper",
+        action: Action::Reply,
+    },
+    Command {
+        name: "nick",
+        aliases: &[],
+        args: &[Arg::required("username", ArgKind::Word)],
+        role: Role::Member,
+        help: "change nickname",
+        action: Action::Nick,
+    },
+    Command {
+        name: "color",
+        aliases: &["colour"],
+        args: &[Arg::required("color", ArgKind::Word)],
+        role: Role::Member,
+        help: "change color",
+        action: Action::Color,
+    },
+    Command {
+        name: "edit",
+        aliases: &[],
+        args: &[Arg::required("text", ArgKind::Text)],
+        role: Role::Member,
+        help: "edit highlighted or last message",
+        action: Action::Edit,
+    },
+    Command {
+        name: "delete",
+        aliases: &["del"],
+        args: &[],
+        role: Role::Member,
+        help: "delete highlighted or last message",
+        action: Action::Delete,
+    },
+    Command {
+        name: "react",
+        aliases: &[],
+        args: &[Arg::required("emoji", ArgKind::Word)],
+        role: Role::Member,
+        help: "toggle reaction on highlighted message",
+        action: Action::React,
+    },
+    Command {
+        name: "away",
+        aliases: &[],
+        args: &[Arg::optional("status", ArgKind::Text)],
+        role: Role::Member,
+        help: "set away",
+        action: Action::Away,
+    },
+    Command {
+        name: "dnd",
+        aliases: &[],
+        args: &[Arg::optional("status", ArgKind::Text)],
+        role: Role::Member,
+        help: "set do not disturb",
+        action: Action::DoNotDisturb,
+    },
+    Command {
+        name: "back",
+        aliases: &[],
+        args: &[],
+        role: Role::Member,
+        help: "set online",
+        action: Action::Back,
+    },
+    Command {
+        name: "ignore",
+        aliases: &[],
+        args: &[Arg::required("username", ArgKind::User)],
+        role: Role::Member,
+        help: "hide user's messages",
+        action: Action::Ignore,
+    },
+    Command {
+        name: "unignore",
+        aliases: &[],
+        args: &[Arg::required("username", ArgKind::User)],
+        role: Role::Member,
+        help: "show user's messages",
+        action: Action::Unignore,
+    },
+    Command {
+        name: "ban",
+        aliases: &[],
+        args: &[Arg::required("username|address", ArgKind::User)],
+        role: Role::Owner,
+        help: "ban user",
+        action: Action::Ban,
+    },
+    Command {
+        name: "topic",
+        aliases: &[],
+        args: &[Arg::optional("text", ArgKind::Text)],
+        role: Role::Owner,
+        help: "set or clear room topic",
+        action: Action::Topic,
+    },
+    Command {
+        name: "motd",
+        aliases: &[],
+        args: &[Arg::optional("text", ArgKind::Text)],
+        role: Role::Owner,
+        help: "set or clear message of the day",
+        action: Action::Motd,
+    },
+    Command {
+        name: "pin",
+        aliases: &[],
+        args: &[],
+        role: Role::Owner,
+        help: "pin or unpin highlighted message",
+        action: Action::Pin,
+    },
+    Command {
+        name: "send",
+        aliases: &[],
+        args: &[Arg::required("path", ArgKind::Word)],
+        role: Role::Member,
+        help: "send file",
+        action: Action::Send,
+    },
+    Command {
+        name: "accept",
+        aliases: &[],
+        args: &[Arg::optional("name", ArgKind::Word)],
+        role: Role::Member,
+        help: "download offered file",
+        action: Action::Accept,
+    },
+    Command {
+        name: "decline",
+        aliases: &[],
+        args: &[Arg::optional("name", ArgKind::Word)],
+        role: Role::Member,
+        help: "dismiss offered file",
+        action: Action::Decline,
+    },
+    Command {
+        name: "fetch",
+        aliases: &[],
+        args: &[Arg::optional("name", ArgKind::Word)],
+        role: Role::Member,
+        help: "download file shared earlier",
+        action: Action::Fetch,
+    },
+    Command {
+        name: "raw",
+        aliases: &[],
+        args: &[],
+        role: Role::Member,
+        help: "toggle markdown rendering",
+        action: Action::Raw,
+    },
+    Command {
+        name: "search",
+        aliases: &[],
+        args: &[Arg::optional("text|/regex/", ArgKind::Text)],
+        role: Role::Member,
+        help: "search message history",
+        action: Action::Search,
+    },
+    Command {
+        name: "compose",
+        aliases: &[],
+        args: &[],
+        role: Role::Member,
+        help: "toggle compose mode, enter inserts a newline",
+        action: Action::Compose,
+    },
+    Command {
+        name: "help",
+        aliases: &["?"],
+        args: &[],
+        role: Role::Member,
+        help: "show this help",
+        action: Action::Help,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS
+        .iter()
+        .find(|command| command.name == name || command.aliases.contains(&name))
+}
+
+pub fn parse(input: &str) -> Option<Result<(&'static Command, Vec<String>), String>> {
+    let body = input.strip_prefix('/')?;
+    let name_end = body.find(char::is_whitespace).unwrap_or(body.len());
+    let name = &body[..name_end];
+    if name.is_empty() || name.contains('/') {
+        return None;
+    }
+
+    let Some(command) = find(&name.to_lowercase()) else {
+        return Some(Err(format!("Unknown command /{}, see /help", name)));
+    };
+    let usage_err = || format!("Usage: {}", command.usage());
+
+    let mut rest = &body[name_end..];
+    let mut args = vec![];
+    for arg in command.args {
+        rest = rest.trim_start();
+        if arg.kind == ArgKind::Text {
+            args.push(rest.trim_end().to_string());
+            rest = "";
+        } else {
+            match next_token(rest) {
+                Ok(Some((token, remaining))) => {
+                    args.push(token);
+                    rest = remaining;
+                }
+                Ok(None) => args.push(String::new()),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+
+        if arg.required && args.last().is_some_and(|arg| arg.is_empty()) {
+            return Some(Err(usage_err()));
+        }
+    }
+
+    if !rest.trim().is_empty() {
+        return Some(Err(usage_err()));
+    }
+    Some(Ok((command, args)))
+}
+
+fn next_token(input: &str) -> Result<Option<(String, &str)>, String> {
+    let mut token = String::new();
+    let mut quote = None;
+    let mut chars = input.char_indices().peekable();
+    let mut end = input.len();
+
+    if chars.peek().is_none() {
+        return Ok(None);
+    }
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (None, c) if c.is_whitespace() => {
+                end = i;
+                break;
+            }
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (Some('\''), c) => token.push(c),
+            (_, '\\') => {
+                if let Some((_, escaped)) = chars.next() {
+                    token.push(escaped);
+                }
+            }
+            (_, c) => token.push(c),
+        }
+    }
+
+    if quote.is_some() {
+        return Err("Unterminated quote".to_string());
+    }
+    Ok(Some((token, &input[end..])))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find, parse, Action, COMMANDS};
+
+    fn parsed(input: &str) -> Option<Result<(Action, Vec<String>), String>> {
+        parse(input).map(|result| result.map(|(command, args)| (command.action, args)))
+    }
+
+    #[test]
+    fn registry() {
+        for command in COMMANDS {
+            assert_eq!(find(command.name), Some(command));
+            for alias in command.aliases {
+                assert_eq!(find(alias), Some(command));
+            }
+        }
+        assert_eq!(
+            find("msg").unwrap().help_line(),
+            "[/msg <username|address> <text>, /w] whisper"
+        );
+        assert_eq!(find("away").unwrap().usage(), "/away [status]");
+    }
+
+    #[test]
+    fn arguments() {
+        assert_eq!(
+            parsed("/msg bob it's  fine\nreally "),
+            Some(Ok((
+                Action::Msg,
+                vec!["bob".into(), "it's  fine\nreally".into()]
+            )))
+        );
+        assert_eq!(
+            parsed("/W bob hi"),
+            Some(Ok((Action::Msg, vec!["bob".into(), "hi".into()])))
+        );
+        assert_eq!(parsed("/away"), Some(Ok((Action::Away, vec!["".into()]))));
+        assert_eq!(parsed("/delete"), Some(Ok((Action::Delete, vec![]))));
+        assert_eq!(parsed("hello /ban bob"), None);
+        assert_eq!(parsed("/home/user is full"), None);
+        assert_eq!(parsed("/ not a command"), None);
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(
+            parsed(r#"/send "my files/report 1.pdf""#),
+            Some(Ok((Action::Send, vec!["my files/report 1.pdf".into()])))
+        );
+        assert_eq!(
+            parsed(r"/send it\'s\ here.txt"),
+            Some(Ok((Action::Send, vec!["it's here.txt".into()])))
+        );
+        assert_eq!(
+            parsed(r#"/accept 'a "b" c'"#),
+            Some(Ok((Action::Accept, vec![r#"a "b" c"#.into()])))
+        );
+        assert_eq!(
+            parsed(r#"/nick "new name"#),
+            Some(Err("Unterminated quote".into()))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parsed("/frobnicate now"),
+            Some(Err("Unknown command /frobnicate, see /help".into()))
+        );
+        assert_eq!(
+            parsed("/msg bob"),
+            Some(Err("Usage: /msg <username|address> <text>".into()))
+        );
+        assert_eq!(
+            parsed("/delete everything"),
+            Some(Err("Usage: /delete".into()))
+        );
+        assert_eq!(
+            parsed("/ban"),
+            Some(Err("Usage: /ban <username|address>".into()))
+        );
+    }
+}
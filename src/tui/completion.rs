@@ -0,0 +1,146 @@
+use super::emoji;
+
+const MAX_EMOJI_CANDIDATES: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub row: usize,
+    pub start: usize,
+    pub candidates: Vec<String>,
+    pub index: usize,
+}
+
+impl Completion {
+    pub fn current(&self) -> &str {
+        &self.candidates[self.index]
+    }
+
+    pub fn end(&self) -> usize {
+        self.start + self.current().chars().count()
+    }
+
+    pub fn cycle(&mut self, forward: bool) {
+        let len = self.candidates.len();
+        self.index = if forward {
+            (self.index + 1) % len
+        } else {
+            (self.index + len - 1) % len
+        };
+    }
+}
+
+pub fn complete(
+    before_cursor: &str,
+    users: &[String],
+    commands: &[(String, bool)],
+) -> Option<(usize, Vec<String>)> {
+    let start = before_cursor
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let word = &before_cursor[start..];
+    let matching_users = |query: &str, prefix: &str| {
+        let query = query.to_lowercase();
+        users
+            .iter()
+            .filter(|user| user.to_lowercase().starts_with(&query))
+            .map(|user| format!("{}{}", prefix, user))
+            .collect::<Vec<String>>()
+    };
+
+    let candidates = if let Some(query) = word.strip_prefix('@') {
+        matching_users(query, "@")
+    } else if start == 0 && word.starts_with('/') {
+        commands
+            .iter()
+            .map(|(name, _)| format!("/{} ", name))
+            .filter(|command| command.starts_with(word))
+            .collect()
+    } else if let Some(query) = word
+        .strip_prefix(':')
+        .filter(|query| !query.is_empty() && !query.contains(':'))
+    {
+        emoji::search(query)
+            .into_iter()
+            .take(MAX_EMOJI_CANDIDATES)
+            .map(|(shortcode, _)| format!(":{}:", shortcode))
+            .collect()
+    } else {
+        let mut args = before_cursor[..start].split_whitespace();
+        let command = args.next()?.strip_prefix('/')?;
+        let takes_username = commands
+            .iter()
+            .any(|(name, takes_username)| name == command && *takes_username);
+        if !takes_username || args.next().is_some() {
+            return None;
+        }
+        matching_users(word, "")
+    };
+
+    (!candidates.is_empty()).then(|| (before_cursor[..start].chars().count(), candidates))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{complete, Completion};
+
+    fn users() -> Vec<String> {
+        vec!["alice".into(), "Albert".into(), "user1a2b".into()]
+    }
+
+    fn commands() -> Vec<(String, bool)> {
+        vec![
+            ("ban".into(), true),
+            ("back".into(), false),
+            ("msg".into(), true),
+        ]
+    }
+
+    #[test]
+    fn mentions_and_arguments() {
+        assert_eq!(
+            complete("hi @al", &users(), &commands()),
+            Some((3, vec!["@alice".into(), "@Albert".into()]))
+        );
+        assert_eq!(
+            complete("/msg us", &users(), &commands()),
+            Some((5, vec!["user1a2b".into()]))
+        );
+        assert_eq!(complete("/msg user1a2b hi al", &users(), &commands()), None);
+        assert_eq!(complete("/back al", &users(), &commands()), None);
+        assert_eq!(complete("hi al", &users(), &commands()), None);
+    }
+
+    #[test]
+    fn commands_and_emoji() {
+        assert_eq!(
+            complete("/ba", &users(), &commands()),
+            Some((0, vec!["/ban ".into(), "/back ".into()]))
+        );
+        assert_eq!(complete("not /ba", &users(), &commands()), None);
+        assert_eq!(
+            complete("ship it :rock", &users(), &commands())
+                .map(|(start, candidates)| (start, candidates.first().cloned())),
+            Some((8, Some(":rocket:".into())))
+        );
+        assert_eq!(complete("12:30", &users(), &commands()), None);
+    }
+
+    #[test]
+    fn cycling() {
+        let mut completion = Completion {
+            row: 0,
+            start: 3,
+            candidates: vec!["@alice".into(), "@Albert".into()],
+            index: 0,
+        };
+        assert_eq!(completion.end(), 9);
+        completion.cycle(true);
+        assert_eq!(completion.current(), "@Albert");
+        completion.cycle(true);
+        assert_eq!(completion.current(), "@alice");
+        completion.cycle(false);
+        assert_eq!(completion.current(), "@Albert");
+    }
+}
@@ -0,0 +1,364 @@
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+
+pub const EMOJIS: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("thumbsup", "👍"),
+    ("-1", "👎"),
+    ("thumbsdown", "👎"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("grin", "😁"),
+    ("grinning", "😀"),
+    ("laughing", "😆"),
+    ("joy", "😂"),
+    ("rofl", "🤣"),
+    ("sweat_smile", "😅"),
+    ("slightly_smiling_face", "🙂"),
+    ("upside_down_face", "🙃"),
+    ("wink", "😉"),
+    ("blush", "😊"),
+    ("innocent", "😇"),
+    ("heart_eyes", "😍"),
+    ("star_struck", "🤩"),
+    ("kissing_heart", "😘"),
+    ("yum", "😋"),
+    ("stuck_out_tongue", "😛"),
+    ("stuck_out_tongue_winking_eye", "😜"),
+    ("zany_face", "🤪"),
+    ("hugs", "🤗"),
+    ("thinking", "🤔"),
+    ("shushing_face", "🤫"),
+    ("zipper_mouth_face", "🤐"),
+    ("raised_eyebrow", "🤨"),
+    ("neutral_face", "😐"),
+    ("expressionless", "😑"),
+    ("no_mouth", "😶"),
+    ("smirk", "😏"),
+    ("unamused", "😒"),
+    ("roll_eyes", "🙄"),
+    ("grimacing", "😬"),
+    ("relieved", "😌"),
+    ("pensive", "😔"),
+    ("sleepy", "😪"),
+    ("sleeping", "😴"),
+    ("mask", "😷"),
+    ("nerd_face", "🤓"),
+    ("sunglasses", "😎"),
+    ("confused", "😕"),
+    ("worried", "😟"),
+    ("slightly_frowning_face", "🙁"),
+    ("open_mouth", "😮"),
+    ("hushed", "😯"),
+    ("astonished", "😲"),
+    ("flushed", "😳"),
+    ("pleading_face", "🥺"),
+    ("cry", "😢"),
+    ("sob", "😭"),
+    ("scream", "😱"),
+    ("confounded", "😖"),
+    ("disappointed", "😞"),
+    ("sweat", "😓"),
+    ("weary", "😩"),
+    ("tired_face", "😫"),
+    ("yawning_face", "🥱"),
+    ("triumph", "😤"),
+    ("rage", "😡"),
+    ("angry", "😠"),
+    ("cursing_face", "🤬"),
+    ("smiling_imp", "😈"),
+    ("skull", "💀"),
+    ("poop", "💩"),
+    ("clown_face", "🤡"),
+    ("ghost", "👻"),
+    ("alien", "👽"),
+    ("robot", "🤖"),
+    ("see_no_evil", "🙈"),
+    ("hear_no_evil", "🙉"),
+    ("speak_no_evil", "🙊"),
+    ("wave", "👋"),
+    ("raised_hand", "✋"),
+    ("ok_hand", "👌"),
+    ("v", "✌️"),
+    ("crossed_fingers", "🤞"),
+    ("point_up", "☝️"),
+    ("point_down", "👇"),
+    ("point_left", "👈"),
+    ("point_right", "👉"),
+    ("fist", "✊"),
+    ("punch", "👊"),
+    ("clap", "👏"),
+    ("raised_hands", "🙌"),
+    ("open_hands", "👐"),
+    ("handshake", "🤝"),
+    ("pray", "🙏"),
+    ("muscle", "💪"),
+    ("eyes", "👀"),
+    ("brain", "🧠"),
+    ("heart", "❤️"),
+    ("orange_heart", "🧡"),
+    ("yellow_heart", "💛"),
+    ("green_heart", "💚"),
+    ("blue_heart", "💙"),
+    ("purple_heart", "💜"),
+    ("black_heart", "🖤"),
+    ("broken_heart", "💔"),
+    ("sparkling_heart", "💖"),
+    ("100", "💯"),
+    ("boom", "💥"),
+    ("zzz", "💤"),
+    ("fire", "🔥"),
+    ("sparkles", "✨"),
+    ("star", "⭐"),
+    ("zap", "⚡"),
+    ("snowflake", "❄️"),
+    ("sunny", "☀️"),
+    ("cloud", "☁️"),
+    ("umbrella", "☔"),
+    ("rainbow", "🌈"),
+    ("earth_africa", "🌍"),
+    ("rocket", "🚀"),
+    ("airplane", "✈️"),
+    ("car", "🚗"),
+    ("bike", "🚲"),
+    ("ship", "🚢"),
+    ("tada", "🎉"),
+    ("confetti_ball", "🎊"),
+    ("balloon", "🎈"),
+    ("gift", "🎁"),
+    ("trophy", "🏆"),
+    ("medal_sports", "🏅"),
+    ("dart", "🎯"),
+    ("video_game", "🎮"),
+    ("musical_note", "🎵"),
+    ("headphones", "🎧"),
+    ("coffee", "☕"),
+    ("tea", "🍵"),
+    ("beer", "🍺"),
+    ("beers", "🍻"),
+    ("wine_glass", "🍷"),
+    ("pizza", "🍕"),
+    ("hamburger", "🍔"),
+    ("fries", "🍟"),
+    ("taco", "🌮"),
+    ("cake", "🍰"),
+    ("cookie", "🍪"),
+    ("apple", "🍎"),
+    ("banana", "🍌"),
+    ("avocado", "🥑"),
+    ("crab", "🦀"),
+    ("snake", "🐍"),
+    ("bug", "🐛"),
+    ("bee", "🐝"),
+    ("cat", "🐱"),
+    ("dog", "🐶"),
+    ("fox_face", "🦊"),
+    ("penguin", "🐧"),
+    ("unicorn", "🦄"),
+    ("turtle", "🐢"),
+    ("seedling", "🌱"),
+    ("evergreen_tree", "🌲"),
+    ("cactus", "🌵"),
+    ("sunflower", "🌻"),
+    ("rose", "🌹"),
+    ("computer", "💻"),
+    ("keyboard", "⌨️"),
+    ("desktop_computer", "🖥️"),
+    ("iphone", "📱"),
+    ("floppy_disk", "💾"),
+    ("cd", "💿"),
+    ("bulb", "💡"),
+    ("wrench", "🔧"),
+    ("hammer", "🔨"),
+    ("hammer_and_wrench", "🛠️"),
+    ("gear", "⚙️"),
+    ("link", "🔗"),
+    ("lock", "🔒"),
+    ("unlock", "🔓"),
+    ("key", "🔑"),
+    ("mag", "🔍"),
+    ("bell", "🔔"),
+    ("no_bell", "🔕"),
+    ("mega", "📣"),
+    ("loudspeaker", "📢"),
+    ("email", "📧"),
+    ("inbox_tray", "📥"),
+    ("outbox_tray", "📤"),
+    ("package", "📦"),
+    ("memo", "📝"),
+    ("pencil2", "✏️"),
+    ("book", "📖"),
+    ("books", "📚"),
+    ("bookmark", "🔖"),
+    ("calendar", "📆"),
+    ("chart_with_upwards_trend", "📈"),
+    ("chart_with_downwards_trend", "📉"),
+    ("bar_chart", "📊"),
+    ("clipboard", "📋"),
+    ("pushpin", "📌"),
+    ("paperclip", "📎"),
+    ("scissors", "✂️"),
+    ("file_folder", "📁"),
+    ("wastebasket", "🗑️"),
+    ("hourglass", "⌛"),
+    ("stopwatch", "⏱️"),
+    ("alarm_clock", "⏰"),
+    ("construction", "🚧"),
+    ("rotating_light", "🚨"),
+    ("warning", "⚠️"),
+    ("no_entry", "⛔"),
+    ("x", "❌"),
+    ("white_check_mark", "✅"),
+    ("heavy_check_mark", "✔️"),
+    ("ballot_box_with_check", "☑️"),
+    ("question", "❓"),
+    ("exclamation", "❗"),
+    ("bangbang", "‼️"),
+    ("heavy_plus_sign", "➕"),
+    ("heavy_minus_sign", "➖"),
+    ("arrow_up", "⬆️"),
+    ("arrow_down", "⬇️"),
+    ("arrow_left", "⬅️"),
+    ("arrow_right", "➡️"),
+    ("repeat", "🔁"),
+    ("recycle", "♻️"),
+    ("red_circle", "🔴"),
+    ("green_circle", "🟢"),
+    ("large_blue_circle", "🔵"),
+    ("checkered_flag", "🏁"),
+];
+
+static SHORTCODE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r":([a-z0-9_+-]+):").unwrap());
+
+pub fn find(name: &str) -> Option<&'static str> {
+    EMOJIS
+        .iter()
+        .find(|(shortcode, _)| *shortcode == name)
+        .map(|(_, emoji)| *emoji)
+}
+
+pub fn is_emoji(text: &str) -> bool {
+    EMOJIS.iter().any(|(_, emoji)| *emoji == text)
+}
+
+pub fn expand_shortcodes(text: &str) -> String {
+    let mut is_code_block = false;
+    text.split('\n')
+        .map(|line| {
+            if line.trim_start().starts_with("```") {
+                is_code_block = !is_code_block;
+                return line.to_string();
+            }
+            if is_code_block {
+                return line.to_string();
+            }
+            line.split('`')
+                .enumerate()
+                .map(|(i, part)| {
+                    if i % 2 == 1 {
+                        part.to_string()
+                    } else {
+                        SHORTCODE
+                            .replace_all(part, |caps: &Captures| {
+                                find(&caps[1]).unwrap_or(&caps[0]).to_string()
+                            })
+                            .to_string()
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join("`")
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn trailing_shortcode(text: &str) -> Option<(usize, &'static str)> {
+    let name = text.strip_suffix(':')?.rsplit_once(':')?.1;
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace()) {
+        return None;
+    }
+    find(name).map(|emoji| (name.chars().count() + 2, emoji))
+}
+
+pub fn search(query: &str) -> Vec<(&'static str, &'static str)> {
+    let query = query.to_lowercase();
+    let mut matches = EMOJIS
+        .iter()
+        .filter_map(|&(shortcode, emoji)| {
+            fuzzy_score(shortcode, &query).map(|score| (score, shortcode, emoji))
+        })
+        .collect::<Vec<(usize, &str, &str)>>();
+    matches.sort_by_key(|(score, shortcode, _)| (*score, shortcode.len()));
+    matches
+        .into_iter()
+        .map(|(_, shortcode, emoji)| (shortcode, emoji))
+        .collect()
+}
+
+fn fuzzy_score(shortcode: &str, query: &str) -> Option<usize> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    if shortcode.starts_with(query) {
+        return Some(0);
+    }
+    if let Some(pos) = shortcode.find(query) {
+        return Some(1 + pos);
+    }
+
+    let mut gaps = 0;
+    let mut chars = shortcode.chars();
+    for query_char in query.chars() {
+        gaps += chars.position(|c| c == query_char)?;
+    }
+    Some(100 + gaps)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{expand_shortcodes, is_emoji, search, trailing_shortcode};
+
+    #[test]
+    fn shortcode_expansion() {
+        assert_eq!(
+            expand_shortcodes("ship it :rocket: :tada:"),
+            "ship it 🚀 🎉"
+        );
+        assert_eq!(
+            expand_shortcodes(":not_an_emoji: 12:30:45"),
+            ":not_an_emoji: 12:30:45"
+        );
+        assert_eq!(expand_shortcodes("`:fire:` :fire:"), "`:fire:` 🔥");
+        assert_eq!(
+            expand_shortcodes("```\n:fire:\n```\n:fire:"),
+            "```\n:fire:\n```\n🔥"
+        );
+    }
+
+    #[test]
+    fn trailing_shortcodes() {
+        assert_eq!(trailing_shortcode("nice :+1:"), Some((4, "👍")));
+        assert_eq!(trailing_shortcode("nice :+1"), None);
+        assert_eq!(trailing_shortcode("a: b c:"), None);
+        assert_eq!(trailing_shortcode("::"), None);
+    }
+
+    #[test]
+    fn emoji_validation() {
+        assert!(is_emoji("👍"));
+        assert!(is_emoji("🚀"));
+        assert!(!is_emoji(":+1:"));
+        assert!(!is_emoji("lol"));
+        assert!(!is_emoji("👍👍"));
+    }
+
+    #[test]
+    fn fuzzy_search() {
+        assert_eq!(search("rock").first(), Some(&("rocket", "🚀")));
+        assert_eq!(search("thup").first(), Some(&("thumbsup", "👍")));
+        assert!(search("tada")
+            .iter()
+            .any(|(shortcode, _)| *shortcode == "tada"));
+        assert!(search("zzzzq").is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+use ratatui::prelude::*;
+use std::sync::LazyLock;
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{self, FontStyle, Theme, ThemeSet},
+    parsing::{SyntaxReference, SyntaxSet},
+};
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+static THEMES: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+
+fn theme(light_mode: bool) -> &'static Theme {
+    if light_mode {
+        &THEMES.themes["base16-ocean.light"]
+    } else {
+        &THEMES.themes["base16-ocean.dark"]
+    }
+}
+
+pub fn find_syntax(lang: &str) -> Option<&'static SyntaxReference> {
+    SYNTAXES.find_syntax_by_token(lang)
+}
+
+fn token_style(style: highlighting::Style) -> Style {
+    let mut token_style = Style::new().fg(Color::Rgb(
+        style.foreground.r,
+        style.foreground.g,
+        style.foreground.b,
+    ));
+    if style.font_style.contains(FontStyle::BOLD) {
+        token_style = token_style.bold();
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        token_style = token_style.italic();
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        token_style = token_style.underlined();
+    }
+    token_style
+}
+
+pub fn highlight_lines<'a>(
+    syntax: &SyntaxReference,
+    lines: &[String],
+    base_style: Style,
+    light_mode: bool,
+) -> Vec<Vec<Span<'a>>> {
+    let mut highlighter = HighlightLines::new(syntax, theme(light_mode));
+    lines
+        .iter()
+        .map(
+            |line| match highlighter.highlight_line(&format!("{}\n", line), &SYNTAXES) {
+                Ok(tokens) => tokens
+                    .into_iter()
+                    .map(|(style, text)| {
+                        Span::from(text.trim_end_matches('\n').to_string())
+                            .style(base_style.patch(token_style(style)))
+                    })
+                    .filter(|span| !span.content.is_empty())
+                    .collect(),
+                Err(_) => vec![Span::from(line.clone()).style(base_style)],
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find_syntax, highlight_lines};
+    use ratatui::prelude::*;
+
+    #[test]
+    fn syntax_lookup() {
+        assert_eq!(find_syntax("rs").unwrap().name, "Rust");
+        assert_eq!(find_syntax("Rust").unwrap().name, "Rust");
+        assert!(find_syntax("yml").is_some());
+        assert!(find_syntax("brainfuck").is_none());
+    }
+
+    #[test]
+    fn block_highlighting() {
+        let base_style = Style::new().bg(Color::Rgb(40, 40, 40));
+        let lines = vec![
+            r#"let s = "a // b"; // note"#.to_string(),
+            "/* open".to_string(),
+            "still comment */".to_string(),
+        ];
+        let highlighted = highlight_lines(find_syntax("rust").unwrap(), &lines, base_style, false);
+        assert_eq!(highlighted.len(), lines.len());
+        for (spans, line) in highlighted.iter().zip(&lines) {
+            assert_eq!(
+                spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect::<String>(),
+                *line
+            );
+            assert!(spans
+                .iter()
+                .all(|span| span.style.bg == base_style.bg && span.style.fg.is_some()));
+        }
+
+        let style_of = |spans: &[Span], text: &str| {
+            spans
+                .iter()
+                .find(|span| span.content.contains(text))
+                .unwrap()
+                .style
+        };
+        assert_ne!(
+            style_of(&highlighted[0], "let"),
+            style_of(&highlighted[0], "a // b")
+        );
+        assert_ne!(
+            style_of(&highlighted[0], "let"),
+            style_of(&highlighted[0], "note")
+        );
+        assert_eq!(
+            style_of(&highlighted[1], "open"),
+            style_of(&highlighted[2], "still comment")
+        );
+
+        let light = highlight_lines(find_syntax("rust").unwrap(), &lines, base_style, true);
+        assert_ne!(
+            style_of(&light[0], " s ").fg,
+            style_of(&highlighted[0], " s ").fg
+        );
+    }
+}
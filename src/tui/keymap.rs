@@ -0,0 +1,429 @@
+use crate::schema::KeymapPreset;
+use crossterm::event::{KeyCode, KeyModifiers};
+use log::warn;
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyAction {
+    Quit,
+    UserList,
+    Help,
+    ScrollUp,
+    ScrollDown,
+    PageUp,
+    PageDown,
+    ScrollTop,
+    ScrollBottom,
+    Reply,
+    Acknowledge,
+    Mentions,
+    Pins,
+    EmojiPicker,
+    Search,
+    SearchNext,
+    SearchPrevious,
+    Copy,
+    Paste,
+    FocusInput,
+    Cancel,
+    HistoryPrevious,
+    HistoryNext,
+    Send,
+    Complete,
+}
+
+pub const ACTIONS: &[(KeyAction, &str, &str)] = &[
+    (KeyAction::Quit, "quit", "exit"),
+    (KeyAction::UserList, "user_list", "user list"),
+    (KeyAction::Help, "help", "help"),
+    (KeyAction::ScrollUp, "scroll_up", "scroll up"),
+    (KeyAction::ScrollDown, "scroll_down", "scroll down"),
+    (KeyAction::PageUp, "page_up", "scroll up a page"),
+    (KeyAction::PageDown, "page_down", "scroll down a page"),
+    (KeyAction::ScrollTop, "scroll_top", "oldest message"),
+    (
+        KeyAction::ScrollBottom,
+        "scroll_bottom",
+        "jump to latest message",
+    ),
+    (KeyAction::Reply, "reply", "reply to highlighted message"),
+    (
+        KeyAction::Acknowledge,
+        "acknowledge",
+        "acknowledge highlighted message",
+    ),
+    (KeyAction::Mentions, "mentions", "mentions"),
+    (KeyAction::Pins, "pins", "pinned messages"),
+    (KeyAction::EmojiPicker, "emoji_picker", "emoji picker"),
+    (KeyAction::Search, "search", "search history"),
+    (KeyAction::SearchNext, "search_next", "next older match"),
+    (
+        KeyAction::SearchPrevious,
+        "search_previous",
+        "next newer match",
+    ),
+    (KeyAction::Copy, "copy", "copy"),
+    (KeyAction::Paste, "paste", "paste"),
+    (KeyAction::FocusInput, "focus_input", "back to typing"),
+    (KeyAction::Cancel, "cancel", "cancel reply"),
+    (
+        KeyAction::HistoryPrevious,
+        "history_previous",
+        "previous input",
+    ),
+    (KeyAction::HistoryNext, "history_next", "next input"),
+    (KeyAction::Send, "send", "send in compose mode"),
+    (
+        KeyAction::Complete,
+        "complete",
+        "complete username, command or emoji",
+    ),
+];
+
+const DEFAULT_BINDINGS: &[(&str, KeyAction)] = &[
+    ("ctrl+q", KeyAction::Quit),
+    ("ctrl+l", KeyAction::UserList),
+    ("f1", KeyAction::Help),
+    ("ctrl+k", KeyAction::ScrollUp),
+    ("ctrl+j", KeyAction::ScrollDown),
+    ("pageup", KeyAction::PageUp),
+    ("pagedown", KeyAction::PageDown),
+    ("ctrl+home", KeyAction::ScrollTop),
+    ("ctrl+end", KeyAction::ScrollBottom),
+    ("ctrl+r", KeyAction::Reply),
+    ("ctrl+a", KeyAction::Acknowledge),
+    ("ctrl+n", KeyAction::Mentions),
+    ("ctrl+t", KeyAction::Pins),
+    ("ctrl+e", KeyAction::EmojiPicker),
+    ("ctrl+f", KeyAction::Search),
+    ("ctrl+y", KeyAction::Copy),
+    ("ctrl+p", KeyAction::Paste),
+    ("esc", KeyAction::Cancel),
+    ("up", KeyAction::HistoryPrevious),
+    ("down", KeyAction::HistoryNext),
+    ("alt+enter", KeyAction::Send),
+    ("tab", KeyAction::Complete),
+];
+
+const NAVIGATION_BINDINGS: &[(&str, KeyAction)] = &[
+    ("home", KeyAction::ScrollTop),
+    ("end", KeyAction::ScrollBottom),
+    ("n", KeyAction::SearchNext),
+    ("N", KeyAction::SearchPrevious),
+];
+
+const VI_NAVIGATION_BINDINGS: &[(&str, KeyAction)] = &[
+    ("k", KeyAction::ScrollUp),
+    ("j", KeyAction::ScrollDown),
+    ("g", KeyAction::ScrollTop),
+    ("G", KeyAction::ScrollBottom),
+    ("r", KeyAction::Reply),
+    ("a", KeyAction::Acknowledge),
+    ("i", KeyAction::FocusInput),
+    ("/", KeyAction::Search),
+];
+
+impl FromStr for KeyAction {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        ACTIONS
+            .iter()
+            .find(|(_, action_name, _)| *action_name == name)
+            .map(|(action, _, _)| *action)
+            .ok_or(format!("No such key action: {}", name))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn matches(&self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        let ignored = match code {
+            KeyCode::Char(_) => KeyModifiers::SHIFT,
+            _ => KeyModifiers::NONE,
+        };
+        self.code == code && self.modifiers.difference(ignored) == modifiers.difference(ignored)
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(binding: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Invalid key binding: {}", binding);
+        let mut parts = binding.split('+').collect::<Vec<&str>>();
+        let key = match parts.pop() {
+            Some("") if binding.ends_with("++") || binding == "+" => {
+                parts.pop();
+                "+"
+            }
+            Some(key) => key,
+            None => return Err(err()),
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(err()),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "esc" => KeyCode::Esc,
+                "enter" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "insert" => KeyCode::Insert,
+                "delete" | "del" => KeyCode::Delete,
+                key => key
+                    .strip_prefix('f')
+                    .and_then(|n| u8::from_str(n).ok())
+                    .filter(|n| (1..=12).contains(n))
+                    .map(KeyCode::F)
+                    .ok_or_else(err)?,
+            },
+        };
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl+"),
+            (KeyModifiers::ALT, "alt+"),
+            (KeyModifiers::SHIFT, "shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}", name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            code => write!(f, "{}", format!("{:?}", code).to_lowercase()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    global: Vec<(KeyBinding, KeyAction)>,
+    navigation: Vec<(KeyBinding, KeyAction)>,
+}
+
+impl Keymap {
+    pub fn new(preset: KeymapPreset, overrides: &[String]) -> Self {
+        let parse = |bindings: &[(&str, KeyAction)]| {
+            bindings
+                .iter()
+                .map(|(binding, action)| (KeyBinding::from_str(binding).unwrap(), *action))
+                .collect::<Vec<(KeyBinding, KeyAction)>>()
+        };
+        let mut keymap = Self {
+            global: parse(DEFAULT_BINDINGS),
+            navigation: match preset {
+                KeymapPreset::Default => parse(NAVIGATION_BINDINGS),
+                KeymapPreset::Vi => [VI_NAVIGATION_BINDINGS, NAVIGATION_BINDINGS]
+                    .into_iter()
+                    .flat_map(parse)
+                    .collect(),
+            },
+        };
+
+        for entry in overrides {
+            match parse_override(entry) {
+                Ok((action, binding)) => {
+                    keymap.global.retain(|(bound, bound_action)| {
+                        *bound_action != action && *bound != binding
+                    });
+                    keymap.global.push((binding, action));
+                }
+                Err(err) => warn!("{}", err),
+            }
+        }
+        keymap
+    }
+
+    pub fn action(
+        &self,
+        code: KeyCode,
+        modifiers: KeyModifiers,
+        navigating: bool,
+    ) -> Option<KeyAction> {
+        let navigation = self.navigation.iter().filter(|_| navigating);
+        navigation
+            .chain(self.global.iter())
+            .find(|(binding, _)| binding.matches(code, modifiers))
+            .map(|(_, action)| *action)
+    }
+
+    pub fn binding(&self, action: KeyAction) -> Option<KeyBinding> {
+        self.global
+            .iter()
+            .find(|(_, bound_action)| *bound_action == action)
+            .map(|(binding, _)| *binding)
+    }
+
+    pub fn help_lines(&self) -> Vec<String> {
+        let describe = |bindings: &[(KeyBinding, KeyAction)], suffix: &str| {
+            ACTIONS
+                .iter()
+                .filter_map(|(action, _, description)| {
+                    let keys = bindings
+                        .iter()
+                        .filter(|(_, bound_action)| bound_action == action)
+                        .map(|(binding, _)| binding.to_string())
+                        .collect::<Vec<String>>();
+                    (!keys.is_empty())
+                        .then(|| format!("[{}] {}{}", keys.join(", "), description, suffix))
+                })
+                .collect::<Vec<String>>()
+        };
+
+        let mut lines = describe(&self.global, "");
+        lines.extend(describe(&self.navigation, " while navigating"));
+        lines
+    }
+}
+
+pub fn parse_override(entry: &str) -> Result<(KeyAction, KeyBinding), String> {
+    let (action, binding) = entry
+        .split_once('=')
+        .ok_or(format!("Expected <action>=<key>, got: {}", entry))?;
+    Ok((
+        KeyAction::from_str(action.trim())?,
+        KeyBinding::from_str(binding.trim())?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_override, KeyAction, KeyBinding, Keymap};
+    use crate::schema::KeymapPreset;
+    use crossterm::event::{KeyCode, KeyModifiers};
+    use std::str::FromStr;
+
+    #[test]
+    fn binding_parsing() {
+        for binding in [
+            "ctrl+q",
+            "alt+enter",
+            "f1",
+            "G",
+            "ctrl+alt+k",
+            "pageup",
+            "space",
+            "+",
+        ] {
+            assert_eq!(KeyBinding::from_str(binding).unwrap().to_string(), binding);
+        }
+        assert_eq!(
+            KeyBinding::from_str("Ctrl+Esc"),
+            Ok(KeyBinding {
+                code: KeyCode::Esc,
+                modifiers: KeyModifiers::CONTROL,
+            })
+        );
+        assert!(KeyBinding::from_str("hyper+k").is_err());
+        assert!(KeyBinding::from_str("f13").is_err());
+        assert!(parse_override("help").is_err());
+        assert!(parse_override("dance=ctrl+d").is_err());
+    }
+
+    #[test]
+    fn default_keymap() {
+        let keymap = Keymap::new(KeymapPreset::Default, &[]);
+        assert_eq!(
+            keymap.action(KeyCode::Char('q'), KeyModifiers::CONTROL, false),
+            Some(KeyAction::Quit)
+        );
+        assert_eq!(
+            keymap.action(KeyCode::Char('h'), KeyModifiers::CONTROL, false),
+            None
+        );
+        assert_eq!(
+            keymap.action(KeyCode::Char('j'), KeyModifiers::NONE, true),
+            None
+        );
+        assert_eq!(
+            keymap.action(KeyCode::End, KeyModifiers::NONE, true),
+            Some(KeyAction::ScrollBottom)
+        );
+        assert_eq!(keymap.action(KeyCode::End, KeyModifiers::NONE, false), None);
+        assert_eq!(
+            keymap.action(KeyCode::Home, KeyModifiers::CONTROL, false),
+            Some(KeyAction::ScrollTop)
+        );
+        assert_eq!(keymap.help_lines()[0], "[ctrl+q] exit");
+        assert!(keymap
+            .help_lines()
+            .contains(&"[alt+enter] send in compose mode".to_string()));
+    }
+
+    #[test]
+    fn overrides_and_vi_preset() {
+        let keymap = Keymap::new(
+            KeymapPreset::Vi,
+            &["help = ctrl+g".into(), "quit=ctrl+l".into(), "bogus".into()],
+        );
+        assert_eq!(
+            keymap.action(KeyCode::Char('g'), KeyModifiers::CONTROL, false),
+            Some(KeyAction::Help)
+        );
+        assert_eq!(
+            keymap.action(KeyCode::F(1), KeyModifiers::NONE, false),
+            None
+        );
+        assert_eq!(
+            keymap.action(KeyCode::Char('l'), KeyModifiers::CONTROL, false),
+            Some(KeyAction::Quit)
+        );
+        assert_eq!(keymap.binding(KeyAction::UserList), None);
+
+        assert_eq!(
+            keymap.action(KeyCode::Char('G'), KeyModifiers::SHIFT, true),
+            Some(KeyAction::ScrollBottom)
+        );
+        assert_eq!(
+            keymap.action(KeyCode::Char('j'), KeyModifiers::NONE, false),
+            None
+        );
+        assert!(keymap
+            .help_lines()
+            .contains(&"[j] scroll down while navigating".to_string()));
+
+        let keymap = Keymap::new(KeymapPreset::Default, &["complete=ctrl+o".into()]);
+        assert_eq!(
+            keymap.action(KeyCode::Char('o'), KeyModifiers::CONTROL, false),
+            Some(KeyAction::Complete)
+        );
+        assert_eq!(keymap.action(KeyCode::Tab, KeyModifiers::NONE, false), None);
+        assert!(keymap
+            .help_lines()
+            .contains(&"[ctrl+o] complete username, command or emoji".to_string()));
+    }
+}
@@ -0,0 +1,363 @@
+use super::highlight;
+use ratatui::prelude::*;
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
+
+const TAB_WIDTH: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    Paragraph(Vec<Inline>),
+    Quote(Vec<Inline>),
+    Bullet(Vec<Inline>),
+    Code {
+        lang: Option<String>,
+        lines: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Inline {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+}
+
+#[derive(Clone, Copy, Default)]
+struct InlineStyle {
+    bold: bool,
+    italic: bool,
+}
+
+pub fn parse(content: &str) -> Vec<Block> {
+    let mut blocks = vec![];
+    let mut code_block: Option<(Option<String>, Vec<String>)> = None;
+
+    for line in content.lines() {
+        if let Some(fence) = line.trim_start().strip_prefix("```") {
+            match code_block.take() {
+                Some((lang, lines)) => blocks.push(Block::Code { lang, lines }),
+                None => {
+                    let lang = Some(fence.trim().to_string()).filter(|lang| !lang.is_empty());
+                    code_block = Some((lang, vec![]));
+                }
+            }
+            continue;
+        }
+
+        if let Some((_, lines)) = code_block.as_mut() {
+            lines.push(line.replace('\t', &" ".repeat(TAB_WIDTH)));
+        } else if let Some(quote) = line.strip_prefix('>') {
+            blocks.push(Block::Quote(parse_inline(
+                quote.strip_prefix(' ').unwrap_or(quote),
+            )));
+        } else if let Some(item) = line
+            .trim_start()
+            .strip_prefix("- ")
+            .or_else(|| line.trim_start().strip_prefix("* "))
+        {
+            blocks.push(Block::Bullet(parse_inline(item)));
+        } else {
+            blocks.push(Block::Paragraph(parse_inline(line)));
+        }
+    }
+
+    if let Some((lang, lines)) = code_block {
+        blocks.push(Block::Code { lang, lines });
+    }
+    blocks
+}
+
+pub fn parse_inline(line: &str) -> Vec<Inline> {
+    let mut inlines = vec![];
+    parse_inline_into(
+        &line.chars().collect::<Vec<char>>(),
+        InlineStyle::default(),
+        &mut inlines,
+    );
+    inlines
+}
+
+fn parse_inline_into(chars: &[char], style: InlineStyle, inlines: &mut Vec<Inline>) {
+    let mut plain = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let marker = chars[i];
+        let closing = match marker {
+            '`' => chars[i + 1..]
+                .iter()
+                .position(|&c| c == '`')
+                .filter(|&len| len > 0)
+                .map(|len| i + 1 + len),
+            '*' | '_' => find_emphasis_end(chars, i),
+            _ => None,
+        };
+
+        let Some(closing) = closing else {
+            plain.push(marker);
+            i += 1;
+            continue;
+        };
+
+        push_text(inlines, &mut plain, style);
+        let inner = &chars[i + 1..closing];
+        match marker {
+            '`' => inlines.push(Inline {
+                text: inner.iter().collect(),
+                bold: style.bold,
+                italic: style.italic,
+                code: true,
+            }),
+            '*' => parse_inline_into(
+                inner,
+                InlineStyle {
+                    bold: true,
+                    ..style
+                },
+                inlines,
+            ),
+            _ => parse_inline_into(
+                inner,
+                InlineStyle {
+                    italic: true,
+                    ..style
+                },
+                inlines,
+            ),
+        }
+        i = closing + 1;
+    }
+
+    push_text(inlines, &mut plain, style);
+}
+
+fn find_emphasis_end(chars: &[char], start: usize) -> Option<usize> {
+    let marker = chars[start];
+    let is_word_char = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric());
+
+    if is_word_char(start.checked_sub(1).and_then(|i| chars.get(i))) {
+        return None;
+    }
+    if chars.get(start + 1).is_none_or(|c| c.is_whitespace()) {
+        return None;
+    }
+
+    (start + 2..chars.len()).find(|&end| {
+        chars[end] == marker && !chars[end - 1].is_whitespace() && !is_word_char(chars.get(end + 1))
+    })
+}
+
+fn push_text(inlines: &mut Vec<Inline>, text: &mut String, style: InlineStyle) {
+    if text.is_empty() {
+        return;
+    }
+    inlines.push(Inline {
+        text: std::mem::take(text),
+        bold: style.bold,
+        italic: style.italic,
+        code: false,
+    });
+}
+
+pub fn render<'a>(
+    blocks: &[Block],
+    highlight: Option<&Regex>,
+    highlight_style: Style,
+    code_style: Style,
+    light_mode: bool,
+) -> Vec<Line<'a>> {
+    let inline_spans = |inlines: &[Inline]| {
+        inlines
+            .iter()
+            .flat_map(|inline| render_inline(inline, highlight, highlight_style, code_style))
+            .collect::<Vec<Span<'a>>>()
+    };
+
+    blocks
+        .iter()
+        .flat_map(|block| match block {
+            Block::Paragraph(inlines) => vec![Line::from(inline_spans(inlines))],
+            Block::Quote(inlines) => {
+                let mut spans = vec![Span::from("│ ").fg(Color::Rgb(75, 75, 75))];
+                spans.extend(inline_spans(inlines).into_iter().map(|span| span.italic()));
+                vec![Line::from(spans)]
+            }
+            Block::Bullet(inlines) => {
+                let mut spans = vec![Span::from(" • ")];
+                spans.extend(inline_spans(inlines));
+                vec![Line::from(spans)]
+            }
+            Block::Code { lang, lines } => {
+                let width = lines.iter().map(|line| line.width()).max().unwrap_or(0);
+                let highlighted = match lang.as_deref().and_then(highlight::find_syntax) {
+                    Some(syntax) => {
+                        highlight::highlight_lines(syntax, lines, code_style, light_mode)
+                    }
+                    None => lines
+                        .iter()
+                        .map(|line| vec![Span::from(line.clone()).style(code_style)])
+                        .collect(),
+                };
+                lines
+                    .iter()
+                    .zip(highlighted)
+                    .map(|(line, highlighted)| {
+                        let padding = " ".repeat(width - line.width() + 1);
+                        let mut spans = vec![Span::from(" ").style(code_style)];
+                        spans.extend(highlighted);
+                        spans.push(Span::from(padding).style(code_style));
+                        Line::from(spans)
+                    })
+                    .collect()
+            }
+        })
+        .collect()
+}
+
+fn render_inline<'a>(
+    inline: &Inline,
+    highlight: Option<&Regex>,
+    highlight_style: Style,
+    code_style: Style,
+) -> Vec<Span<'a>> {
+    let mut style = Style::new();
+    if inline.bold {
+        style = style.bold();
+    }
+    if inline.italic {
+        style = style.italic();
+    }
+    if inline.code {
+        return vec![Span::from(inline.text.clone()).style(style.patch(code_style))];
+    }
+
+    let Some(highlight) = highlight else {
+        return vec![Span::from(inline.text.clone()).style(style)];
+    };
+    let mut spans = vec![];
+    let mut last_end = 0;
+    for found in highlight.find_iter(&inline.text) {
+        if found.start() > last_end {
+            spans.push(Span::from(inline.text[last_end..found.start()].to_string()).style(style));
+        }
+        spans.push(Span::from(found.as_str().to_string()).style(style.patch(highlight_style)));
+        last_end = found.end();
+    }
+    if last_end < inline.text.len() {
+        spans.push(Span::from(inline.text[last_end..].to_string()).style(style));
+    }
+    spans
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, parse_inline, Block, Inline};
+
+    fn plain(text: &str) -> Inline {
+        Inline {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    fn bold(text: &str) -> Inline {
+        Inline {
+            text: text.into(),
+            bold: true,
+            ..Default::default()
+        }
+    }
+
+    fn italic(text: &str) -> Inline {
+        Inline {
+            text: text.into(),
+            italic: true,
+            ..Default::default()
+        }
+    }
+
+    fn code(text: &str) -> Inline {
+        Inline {
+            text: text.into(),
+            code: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn inline_styles() {
+        assert_eq!(
+            parse_inline("a *bold* and _italic_ with `code`"),
+            vec![
+                plain("a "),
+                bold("bold"),
+                plain(" and "),
+                italic("italic"),
+                plain(" with "),
+                code("code"),
+            ]
+        );
+        assert_eq!(
+            parse_inline("*bold _both_*"),
+            vec![
+                bold("bold "),
+                Inline {
+                    text: "both".into(),
+                    bold: true,
+                    italic: true,
+                    code: false,
+                },
+            ]
+        );
+        assert_eq!(parse_inline("`*not bold*`"), vec![code("*not bold*")]);
+    }
+
+    #[test]
+    fn unmatched_markers() {
+        assert_eq!(
+            parse_inline("snake_case_name"),
+            vec![plain("snake_case_name")]
+        );
+        assert_eq!(parse_inline("2 * 3 * 4"), vec![plain("2 * 3 * 4")]);
+        assert_eq!(parse_inline("*open"), vec![plain("*open")]);
+        assert_eq!(parse_inline("``"), vec![plain("``")]);
+        assert_eq!(parse_inline("@user_name_"), vec![plain("@user_name_")]);
+    }
+
+    #[test]
+    fn blocks() {
+        assert_eq!(
+            parse("> quoted *text*\n- first\n* second\nplain"),
+            vec![
+                Block::Quote(vec![plain("quoted "), bold("text")]),
+                Block::Bullet(vec![plain("first")]),
+                Block::Bullet(vec![plain("second")]),
+                Block::Paragraph(vec![plain("plain")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn fenced_code() {
+        assert_eq!(
+            parse("before\n```rust\nfn main() {\n\tlet _x = *y*;\n```\nafter"),
+            vec![
+                Block::Paragraph(vec![plain("before")]),
+                Block::Code {
+                    lang: Some("rust".into()),
+                    lines: vec!["fn main() {".into(), "    let _x = *y*;".into()],
+                },
+                Block::Paragraph(vec![plain("after")]),
+            ]
+        );
+        assert_eq!(
+            parse("```\n  unclosed"),
+            vec![Block::Code {
+                lang: None,
+                lines: vec!["  unclosed".into()],
+            }]
+        );
+    }
+}
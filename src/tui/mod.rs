@@ -1,2 +1,10 @@
 pub mod chat_app;
+pub mod commands;
+pub mod completion;
+pub mod emoji;
+pub mod highlight;
+pub mod keymap;
+pub mod markdown;
+pub mod search;
 pub mod ui;
+pub mod wrap;
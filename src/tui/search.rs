@@ -0,0 +1,209 @@
+use super::ui::MsgItem;
+use ratatui::prelude::*;
+use regex::Regex;
+
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub query: String,
+    pub pattern: Regex,
+    pub hits: Vec<usize>,
+    pub current: Option<usize>,
+}
+
+impl Search {
+    pub fn new(query: &str) -> Result<Self, String> {
+        Ok(Self {
+            query: query.to_string(),
+            pattern: Regex::new(&pattern(query)?).map_err(|err| err.to_string())?,
+            hits: vec![],
+            current: None,
+        })
+    }
+
+    pub fn refresh(&mut self, items: &[MsgItem]) {
+        let pattern = &self.pattern;
+        self.hits = items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| {
+                item.msg
+                    .as_ref()
+                    .is_some_and(|msg| !msg.deleted && pattern.is_match(&msg.content))
+            })
+            .map(|(i, _)| i)
+            .collect();
+        if !self.current.is_some_and(|current| self.is_hit(current)) {
+            self.current = None;
+        }
+    }
+
+    pub fn step(&mut self, from: Option<usize>, older: bool) -> Option<usize> {
+        let (first, last) = (*self.hits.first()?, *self.hits.last()?);
+        let hit = match from {
+            Some(from) if older => self.hits.iter().rev().find(|hit| **hit < from),
+            Some(from) => self.hits.iter().find(|hit| **hit > from),
+            None => None,
+        }
+        .copied()
+        .unwrap_or(if older { last } else { first });
+        self.current = Some(hit);
+        Some(hit)
+    }
+
+    pub fn is_hit(&self, item: usize) -> bool {
+        self.hits.binary_search(&item).is_ok()
+    }
+
+    pub fn status(&self) -> String {
+        let position = self
+            .current
+            .and_then(|current| self.hits.iter().position(|hit| *hit == current))
+            .map_or(0, |position| position + 1);
+        format!("search: {} [{}/{}]", self.query, position, self.hits.len())
+    }
+}
+
+pub fn pattern(query: &str) -> Result<String, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err("Empty search".to_string());
+    }
+    match query
+        .strip_prefix('/')
+        .and_then(|query| query.strip_suffix('/'))
+        .filter(|query| !query.is_empty())
+    {
+        Some(regex) => Regex::new(regex)
+            .map(|_| regex.to_string())
+            .map_err(|_| format!("Invalid pattern: {}", regex)),
+        None => Ok(format!("(?i){}", regex::escape(query))),
+    }
+}
+
+pub fn highlight<'a>(text: &Text<'a>, pattern: &Regex, style: Style) -> Text<'a> {
+    let mut highlighted = text.clone();
+    for line in highlighted.lines.iter_mut() {
+        let content = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect::<String>();
+        let matches = pattern
+            .find_iter(&content)
+            .filter(|found| !found.is_empty())
+            .map(|found| found.range())
+            .collect::<Vec<_>>();
+        if matches.is_empty() {
+            continue;
+        }
+
+        let mut spans = vec![];
+        let mut offset = 0;
+        for span in &line.spans {
+            let end = offset + span.content.len();
+            let mut bounds = vec![offset, end];
+            for found in &matches {
+                bounds.extend(
+                    [found.start, found.end]
+                        .into_iter()
+                        .filter(|i| (offset..end).contains(i)),
+                );
+            }
+            bounds.sort_unstable();
+            bounds.dedup();
+
+            for part in bounds.windows(2) {
+                let is_match = matches
+                    .iter()
+                    .any(|found| found.start <= part[0] && part[1] <= found.end);
+                let part_style = if is_match {
+                    span.style.patch(style)
+                } else {
+                    span.style
+                };
+                spans.push(Span::styled(
+                    content[part[0]..part[1]].to_string(),
+                    part_style,
+                ));
+            }
+            offset = end;
+        }
+        line.spans = spans;
+    }
+    highlighted
+}
+
+#[cfg(test)]
+mod test {
+    use super::{highlight, pattern, Search};
+    use ratatui::prelude::*;
+    use regex::Regex;
+
+    #[test]
+    fn query_patterns() {
+        assert_eq!(pattern("a.b"), Ok(r"(?i)a\.b".into()));
+        assert_eq!(pattern(r"/\d+ files?/"), Ok(r"\d+ files?".into()));
+        assert_eq!(pattern("/home/"), Ok("home".into()));
+        assert_eq!(pattern("/"), Ok("(?i)/".into()));
+        assert!(pattern("/(/").is_err());
+        assert!(pattern("  ").is_err());
+    }
+
+    #[test]
+    fn stepping() {
+        let mut search = Search::new("hi").unwrap();
+        assert_eq!(search.step(None, true), None);
+
+        search.hits = vec![2, 5, 9];
+        assert_eq!(search.step(None, true), Some(9));
+        assert_eq!(search.step(Some(9), true), Some(5));
+        assert_eq!(search.step(Some(4), true), Some(2));
+        assert_eq!(search.step(Some(2), true), Some(9));
+        assert_eq!(search.step(Some(9), false), Some(2));
+        assert_eq!(search.step(Some(3), false), Some(5));
+        assert_eq!(search.status(), "search: hi [2/3]");
+        assert!(search.is_hit(5) && !search.is_hit(4));
+
+        search.refresh(&[]);
+        assert!(search.hits.is_empty());
+        assert_eq!(search.current, None);
+        assert_eq!(search.status(), "search: hi [0/0]");
+    }
+
+    #[test]
+    fn match_highlighting() {
+        let text = Text::from(vec![
+            Line::from(vec![Span::from("ala "), Span::from("ma kota").bold()]),
+            Line::from("zażółć"),
+        ]);
+        let highlighted = highlight(
+            &text,
+            &Regex::new("(?i)A M|ół").unwrap(),
+            Style::new().reversed(),
+        );
+
+        let spans = |line: &Line| {
+            line.spans
+                .iter()
+                .map(|span| (span.content.to_string(), span.style))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            spans(&highlighted.lines[0]),
+            vec![
+                ("al".into(), Style::new()),
+                ("a ".into(), Style::new().reversed()),
+                ("m".into(), Style::new().bold().reversed()),
+                ("a kota".into(), Style::new().bold()),
+            ]
+        );
+        assert_eq!(
+            spans(&highlighted.lines[1]),
+            vec![
+                ("zaż".into(), Style::new()),
+                ("ół".into(), Style::new().reversed()),
+                ("ć".into(), Style::new()),
+            ]
+        );
+    }
+}
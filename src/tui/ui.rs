@@ -1,5 +1,17 @@
-use crate::{schema::TextMessage, tui::chat_app::ChatApp, util::systime_to_string};
+use crate::{
+    network::User,
+    schema::TextMessage,
+    tui::{
+        chat_app::ChatApp,
+        commands::{self, Command},
+        emoji,
+        keymap::{KeyBinding, Keymap},
+        markdown, search, wrap,
+    },
+    util::systime_to_string,
+};
 use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -11,13 +23,40 @@ use ratatui::{
     widgets::*,
 };
 use regex::Regex;
-use std::io;
+use std::{io, net::SocketAddr};
 use tui_pattern_highlighter::highlight_text;
 use tui_popup::{Popup, SizedWrapper};
-use tui_textarea::{CursorMove, Input, TextArea};
+use tui_textarea::{CursorMove, Input, Key, TextArea};
+
+const HELP_INPUT: &str = "[@<username>] mention\n[:shortcode:] emoji";
+const HELP_MOUSE: &str = "[wheel/click] scroll, highlight message or mention from user list";
+
+const USER_LIST_WIDTH: usize = 32;
+const USER_LIST_HEIGHT: usize = 25;
+// Users shown before the rest of the list is collapsed into "..."
+const USER_LIST_ENTRIES: usize = 8;
 
-const HELP_POPUP_CONTENT: &str =
-    "[ctrl+q] exit\n[ctrl+l] user list\n[ctrl+j] scroll down\n[ctrl+k] scroll up\n[/ban <username>] ban user\n[@<username>] mention";
+fn popup_body_area(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.saturating_add(2).min(area.width);
+    let height = height.saturating_add(2).min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+    .inner(Margin::new(1, 1))
+}
+
+fn help_content(keymap: &Keymap, mouse: bool) -> String {
+    let mut lines = keymap.help_lines();
+    lines.push(HELP_INPUT.to_string());
+    if mouse {
+        lines.push(HELP_MOUSE.to_string());
+    }
+    lines.extend(commands::COMMANDS.iter().map(Command::help_line));
+    lines.join("\n")
+}
 
 #[derive(Debug)]
 pub struct Tui<B: Backend> {
@@ -35,6 +74,8 @@ impl<B: Backend> Tui<B> {
     }
 
     pub fn render(app: &mut ChatApp, frame: &mut Frame) {
+        app.msg_area.width = frame.size().width;
+        app.msg_area.update_height();
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints(vec![
@@ -42,19 +83,38 @@ impl<B: Backend> Tui<B> {
                 Constraint::Length(5 + app.msg_area.height),
             ])
             .split(frame.size());
-        app.msg_area.width = layout[0].width;
 
-        let mut msgs_list = List::new(app.messages.items.clone())
-            .block(
-                Block::default()
-                    .title_top(
-                        Line::from(app.client.room.lock().unwrap()._id.clone()).left_aligned(),
-                    )
-                    .title_bottom(Line::from("ctrl+[h]elp").right_aligned())
-                    .borders(Borders::ALL)
-                    .padding(Padding::new(2, 2, 1, 1))
-                    .border_set(border::ROUNDED),
+        let mut msgs_block = Block::default()
+            .title_top(Line::from(app.room_title()).left_aligned())
+            .title_bottom(Line::from(app.help_hint()).right_aligned())
+            .borders(Borders::ALL)
+            .padding(Padding::new(2, 2, 1, 1))
+            .border_set(border::ROUNDED);
+        if let Some(transfer_status) = app.transfer_status() {
+            msgs_block = msgs_block.title_bottom(Line::from(transfer_status).centered());
+        }
+        if let Some(new_msgs_status) = app.new_msgs_status() {
+            msgs_block = msgs_block.title_top(Line::from(new_msgs_status).right_aligned().bold());
+        }
+        if let Some(typing_status) = app.typing_status() {
+            msgs_block = msgs_block.title_bottom(Line::from(typing_status).left_aligned().italic());
+        }
+
+        app.messages_area = msgs_block.inner(layout[0]);
+        let mut msgs_list =
+            List::new(
+                app.messages
+                    .items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| match &app.search {
+                        Some(search) if search.is_hit(i) => {
+                            search::highlight(&item.text, &search.pattern, app.style.search_match)
+                        }
+                        _ => item.text.clone(),
+                    }),
             )
+            .block(msgs_block)
             .style(app.style.block)
             .direction(ListDirection::TopToBottom);
         if app.messages.is_highlighted {
@@ -62,15 +122,27 @@ impl<B: Backend> Tui<B> {
         }
 
         frame.render_stateful_widget(msgs_list, layout[0], &mut app.messages.state);
-        frame.render_widget(app.msg_area.textarea.widget(), layout[1]);
+        if let Some(search_bar) = app.search_bar() {
+            let area = Rect {
+                y: app.messages_area.y.saturating_sub(1),
+                height: 1,
+                ..app.messages_area
+            };
+            frame.render_widget(
+                Paragraph::new(search_bar).style(app.style.block.reversed()),
+                area,
+            );
+        }
+        app.user_list_area = None;
+        app.msg_area.render(frame, layout[1]);
 
         match app.current_popup.clone() {
             PopupState::Help => {
-                let popup_content = Paragraph::new(Text::from(HELP_POPUP_CONTENT));
+                let help_content = help_content(&app.keymap, app.mouse);
                 let help_popup = Popup::new(SizedWrapper {
-                    inner: popup_content,
-                    width: 27,
-                    height: 6,
+                    inner: Paragraph::new(Text::from(help_content.as_str())),
+                    width: help_content.lines().map(str::len).max().unwrap_or(0) + 1,
+                    height: help_content.lines().count(),
                 })
                 .style(app.style.block)
                 .border_set(border::ROUNDED)
@@ -78,58 +150,196 @@ impl<B: Backend> Tui<B> {
                 frame.render_widget(&help_popup, frame.size());
             }
             PopupState::List => {
+                let own_addr = app.client.user.lock().unwrap().addr;
+                let users = app.sorted_users();
+                let mut lines = users
+                    .iter()
+                    .take(USER_LIST_ENTRIES)
+                    .map(|user| {
+                        let status = user
+                            .status
+                            .as_ref()
+                            .map(|status| format!(" - {}", status))
+                            .unwrap_or_default();
+                        Line::from(format!(
+                            "{} {} [{}]{}{}",
+                            user.presence.symbol(),
+                            user.id,
+                            user.addr.unwrap(),
+                            if user.addr == own_addr { "*" } else { "" },
+                            status
+                        ))
+                        .fg(user.color.clone())
+                    })
+                    .collect::<Vec<Line>>();
+                if users.len() > USER_LIST_ENTRIES {
+                    lines.push(Line::from("..."));
+                }
+                let rows = users
+                    .iter()
+                    .take(USER_LIST_ENTRIES)
+                    .map(|user| user.addr.unwrap())
+                    .collect::<Vec<SocketAddr>>();
+
                 let user_list_popup = Popup::new(SizedWrapper {
-                    inner: Paragraph::new(
-                        app.users
-                            .iter()
-                            .enumerate()
-                            .map(|(n, (_, user))| {
-                                if n < 10 - 2 {
-                                    if user.addr.unwrap()
-                                        == app.client.user.lock().unwrap().addr.unwrap()
-                                    {
-                                        Line::from(format!(
-                                            "{} [{}]*",
-                                            user.id,
-                                            user.addr.unwrap().to_string()
-                                        ))
-                                        .fg(user.color.clone())
-                                    } else {
-                                        Line::from(format!(
-                                            "{} [{}]",
-                                            user.id,
-                                            user.addr.unwrap().to_string()
-                                        ))
-                                        .fg(user.color.clone())
-                                    }
-                                } else {
-                                    Line::from("...")
-                                }
-                            })
-                            .collect::<Text>(),
-                    ),
-                    width: 32,
-                    height: 25,
+                    inner: Paragraph::new(Text::from(lines)),
+                    width: USER_LIST_WIDTH,
+                    height: USER_LIST_HEIGHT,
                 })
                 .style(app.style.block)
                 .border_set(border::ROUNDED)
                 .title("users");
                 frame.render_widget(&user_list_popup, frame.size());
+                app.user_list_area = Some(popup_body_area(
+                    frame.size(),
+                    USER_LIST_WIDTH as u16,
+                    USER_LIST_HEIGHT as u16,
+                ));
+                app.user_list_rows = rows;
+            }
+            PopupState::Mentions => {
+                let selected = app.mentions_state.selected();
+                let mentions = if app.mentions.is_empty() {
+                    Text::from("No mentions yet")
+                } else {
+                    app.mentions
+                        .iter()
+                        .enumerate()
+                        .map(|(i, (_, summary))| {
+                            let line = Line::from(summary.clone());
+                            if Some(i) == selected {
+                                line.style(app.style.block.reversed())
+                            } else {
+                                line
+                            }
+                        })
+                        .collect::<Text>()
+                };
+                let height = mentions.lines.len().min(15);
+                let scroll = selected.map_or(0, |i| i.saturating_sub(height - 1));
+                let mentions_popup = Popup::new(SizedWrapper {
+                    inner: Paragraph::new(mentions).scroll((scroll as u16, 0)),
+                    width: 48,
+                    height,
+                })
+                .style(app.style.block)
+                .border_set(border::ROUNDED)
+                .title("mentions");
+                frame.render_widget(&mentions_popup, frame.size());
+            }
+            PopupState::Pins => {
+                let selected = app.pins_state.selected();
+                let pin_summaries = app.pin_summaries();
+                let pins = if pin_summaries.is_empty() {
+                    Text::from("No pinned messages")
+                } else {
+                    pin_summaries
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, summary)| {
+                            let line = Line::from(summary);
+                            if Some(i) == selected {
+                                line.style(app.style.block.reversed())
+                            } else {
+                                line
+                            }
+                        })
+                        .collect::<Text>()
+                };
+                let height = pins.lines.len().min(15);
+                let scroll = selected.map_or(0, |i| i.saturating_sub(height - 1));
+                let pins_popup = Popup::new(SizedWrapper {
+                    inner: Paragraph::new(pins).scroll((scroll as u16, 0)),
+                    width: 48,
+                    height,
+                })
+                .style(app.style.block)
+                .border_set(border::ROUNDED)
+                .title("pins");
+                frame.render_widget(&pins_popup, frame.size());
+            }
+            PopupState::Emoji => {
+                let selected = app.emoji_state.selected();
+                let results = emoji::search(&app.emoji_query);
+                let height = results.len().clamp(1, 15);
+                let scroll = selected.map_or(0, |i| i.saturating_sub(height - 1));
+                let mut lines = vec![Line::from(format!("search: {}", app.emoji_query)).bold()];
+                if results.is_empty() {
+                    lines.push(Line::from("No matching emoji"));
+                }
+                lines.extend(
+                    results
+                        .into_iter()
+                        .enumerate()
+                        .skip(scroll)
+                        .take(height)
+                        .map(|(i, (shortcode, emoji))| {
+                            let line = Line::from(format!("{} :{}:", emoji, shortcode));
+                            if Some(i) == selected {
+                                line.style(app.style.block.reversed())
+                            } else {
+                                line
+                            }
+                        }),
+                );
+                let emoji_popup = Popup::new(SizedWrapper {
+                    inner: Paragraph::new(Text::from(lines)),
+                    width: 32,
+                    height: height + 1,
+                })
+                .style(app.style.block)
+                .border_set(border::ROUNDED)
+                .title("emoji");
+                frame.render_widget(&emoji_popup, frame.size());
+            }
+            PopupState::Completion => {
+                let Some(completion) = app.completion.as_ref() else {
+                    return;
+                };
+                let height = completion.candidates.len().min(10);
+                let scroll = completion.index.saturating_sub(height - 1);
+                let candidates = completion
+                    .candidates
+                    .iter()
+                    .enumerate()
+                    .skip(scroll)
+                    .take(height)
+                    .map(|(i, candidate)| {
+                        let line = Line::from(candidate.trim_end().to_string());
+                        if i == completion.index {
+                            line.style(app.style.block.reversed())
+                        } else {
+                            line
+                        }
+                    })
+                    .collect::<Text>();
+                let completion_popup = Popup::new(SizedWrapper {
+                    inner: Paragraph::new(candidates),
+                    width: 32,
+                    height,
+                })
+                .style(app.style.block)
+                .border_set(border::ROUNDED)
+                .title("completion");
+                frame.render_widget(&completion_popup, frame.size());
             }
             _ => (),
         }
     }
 
-    pub fn term_init(&mut self) -> io::Result<()> {
+    pub fn term_init(&mut self, mouse: bool) -> io::Result<()> {
         enable_raw_mode()?;
         crossterm::execute!(io::stderr(), EnterAlternateScreen)?;
+        if mouse {
+            execute!(io::stdout(), EnableMouseCapture)?;
+        }
         self.terminal.clear()?;
         Ok(())
     }
 
     pub fn term_restore(&mut self) -> io::Result<()> {
         disable_raw_mode()?;
-        execute!(io::stdout(), LeaveAlternateScreen)?;
+        execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen)?;
         Ok(())
     }
 }
@@ -137,6 +347,9 @@ impl<B: Backend> Tui<B> {
 #[derive(Debug)]
 pub struct StatefulArea<'a> {
     pub textarea: TextArea<'a>,
+    block: Block<'a>,
+    title: Option<String>,
+    compose: Option<String>,
     pub height: u16,
     pub width: u16,
 }
@@ -147,13 +360,12 @@ impl<'a> StatefulArea<'a> {
     pub fn new(style: ChatStyle) -> Self {
         let mut textarea = TextArea::default();
         textarea.set_cursor_line_style(style.block);
-        textarea.set_block(
-            Block::default()
-                .borders(Borders::ALL)
-                .set_style(style.block)
-                .padding(Padding::new(2, 2, 1, 1))
-                .border_set(border::ROUNDED),
-        );
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .set_style(style.block)
+            .padding(Padding::new(2, 2, 1, 1))
+            .border_set(border::ROUNDED);
+        textarea.set_block(block.clone());
         textarea.set_search_pattern(r"@\w+").unwrap();
         textarea.set_search_style(style.block.reversed());
         textarea.set_placeholder_text("Start typing...");
@@ -161,44 +373,192 @@ impl<'a> StatefulArea<'a> {
 
         Self {
             textarea,
+            block,
+            title: None,
+            compose: None,
             height: 0,
             width: 0,
         }
     }
 
-    pub fn on_input_update(&mut self, input: Input) {
-        if self.textarea.input_without_shortcuts(input) {
-            self.move_last_word_to_new_line();
+    pub fn set_title(&mut self, title: Option<String>) {
+        self.title = title;
+        self.update_block();
+    }
+
+    pub fn set_compose(&mut self, compose: bool, send_key: Option<KeyBinding>) {
+        self.compose = compose.then(|| match send_key {
+            Some(key) => format!("compose [{}] send", key),
+            None => "compose".to_string(),
+        });
+        self.update_block();
+    }
+
+    fn update_block(&mut self) {
+        let mut block = self.block.clone();
+        if let Some(title) = &self.title {
+            block = block.title_top(Line::from(title.clone()).left_aligned());
+        }
+        if let Some(compose) = &self.compose {
+            block = block.title_bottom(Line::from(compose.clone()).right_aligned());
+        }
+        self.textarea.set_block(block);
+    }
+
+    pub fn text(&self) -> String {
+        self.textarea.lines().join("\n")
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.clear_buffer();
+        self.textarea.insert_str(text);
+    }
+
+    pub fn on_input_update(&mut self, input: Input) -> bool {
+        let is_colon = input.key == Key::Char(':');
+        let modified = self.textarea.input_without_shortcuts(input);
+        if modified && is_colon {
+            self.expand_trailing_shortcode();
         }
+        modified
     }
 
-    fn move_last_word_to_new_line(&mut self) {
-        let line = self.textarea.lines()[self.textarea.cursor().0].clone();
+    pub fn insert_emoji(&mut self, emoji: &str) {
+        self.textarea.insert_str(emoji);
+    }
 
-        let mut insert_nl = false;
-        if line.len() >= (self.width - 6).into() {
-            let rlines: String = line.chars().rev().collect();
-            if let Some(caps) = Regex::new(r"\S+").unwrap().captures(&rlines) {
-                let cap = caps.get(0).unwrap();
-                if cap.start() == 0 {
-                    let rword: String = cap.as_str().chars().rev().collect();
-                    if rword.len() >= (self.width - 6).into() {
-                        self.textarea.delete_char();
-                    } else {
-                        self.textarea.delete_word();
-                        self.textarea.insert_newline();
-                        self.textarea.insert_str(&rword);
-                        insert_nl = true;
-                    }
-                } else {
-                    self.textarea.delete_char();
-                }
+    fn expand_trailing_shortcode(&mut self) {
+        let (row, col) = self.textarea.cursor();
+        let before_cursor = self.textarea.lines()[row]
+            .chars()
+            .take(col)
+            .collect::<String>();
+        if let Some((len, emoji)) = emoji::trailing_shortcode(&before_cursor) {
+            for _ in 0..len {
+                self.textarea.delete_char();
             }
+            self.textarea.insert_str(emoji);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textarea.lines().iter().all(|line| line.is_empty())
+    }
+
+    fn wrap_width(&self) -> usize {
+        self.width.saturating_sub(6).into()
+    }
+
+    pub fn update_height(&mut self) {
+        let width = self.wrap_width();
+        let rows: usize = self
+            .textarea
+            .lines()
+            .iter()
+            .map(|line| wrap::visual_rows(line, width))
+            .sum();
+        self.height = (rows.saturating_sub(1) as u16).min(Self::MAX_AREA_HEIGHT);
+    }
+
+    pub fn move_cursor_up(&mut self) -> bool {
+        self.move_cursor_row(true)
+    }
+
+    pub fn move_cursor_down(&mut self) -> bool {
+        self.move_cursor_row(false)
+    }
+
+    fn move_cursor_row(&mut self, up: bool) -> bool {
+        let width = self.wrap_width();
+        let (row, col) = self.textarea.cursor();
+        let lines = self.textarea.lines();
+        let (visual_row, x) = wrap::cursor_position(&lines[row], width, col);
+
+        let target = if up {
+            if visual_row > 0 {
+                Some((row, visual_row - 1))
+            } else if row > 0 {
+                Some((row - 1, wrap::visual_rows(&lines[row - 1], width) - 1))
+            } else {
+                None
+            }
+        } else if visual_row + 1 < wrap::visual_rows(&lines[row], width) {
+            Some((row, visual_row + 1))
+        } else if row + 1 < lines.len() {
+            Some((row + 1, 0))
+        } else {
+            None
+        };
+
+        let Some((row, visual_row)) = target else {
+            return false;
+        };
+        let col = wrap::col_at(&lines[row], width, visual_row, x);
+        self.textarea
+            .move_cursor(CursorMove::Jump(row as u16, col as u16));
+        true
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let block = self.textarea.block().cloned().unwrap_or_default();
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let width = self.wrap_width();
+        let (cursor_row, cursor_col) = self.textarea.cursor();
+        let mut rows = vec![];
+        let mut cursor = (0, 0);
+        for (i, line) in self.textarea.lines().iter().enumerate() {
+            if i == cursor_row {
+                let (row, x) = wrap::cursor_position(line, width, cursor_col);
+                cursor = (rows.len() + row, x);
+            }
+            rows.extend(
+                wrap::wrap_line(line, width)
+                    .into_iter()
+                    .map(|range| self.highlight_mentions(&line[range])),
+            );
+        }
+
+        let scroll = cursor
+            .0
+            .saturating_sub(inner.height.saturating_sub(1).into());
+        let paragraph = if self.is_empty() {
+            Paragraph::new(self.textarea.placeholder_text())
+                .style(self.textarea.placeholder_style().unwrap_or_default())
+        } else {
+            Paragraph::new(rows)
+                .style(self.textarea.style())
+                .scroll((scroll as u16, 0))
+        };
+        frame.render_widget(paragraph, inner);
+
+        let (x, y) = (cursor.1 as u16, (cursor.0 - scroll) as u16);
+        if x < inner.width && y < inner.height {
+            frame
+                .buffer_mut()
+                .get_mut(inner.x + x, inner.y + y)
+                .set_style(self.textarea.cursor_style());
+        }
+    }
 
-            if self.height <= Self::MAX_AREA_HEIGHT && insert_nl {
-                self.height += 1;
+    fn highlight_mentions<'s>(&self, row: &'s str) -> Line<'s> {
+        let Some(pattern) = self.textarea.search_pattern() else {
+            return Line::from(row);
+        };
+        let mut spans = vec![];
+        let mut last_end = 0;
+        for found in pattern.find_iter(row) {
+            if found.start() > last_end {
+                spans.push(Span::from(&row[last_end..found.start()]));
             }
+            spans.push(Span::from(found.as_str()).style(self.textarea.search_style()));
+            last_end = found.end();
+        }
+        if last_end < row.len() {
+            spans.push(Span::from(&row[last_end..]));
         }
+        Line::from(spans)
     }
 
     pub fn get_text(&mut self) -> Option<String> {
@@ -211,18 +571,7 @@ impl<'a> StatefulArea<'a> {
     }
 
     fn get_buffer(&mut self) -> Option<String> {
-        let lines: String = self
-            .textarea
-            .lines()
-            .iter()
-            .map(|line| {
-                let mut line_ = line.to_string();
-                if !line_.is_empty() && line_ != *self.textarea.lines().last().unwrap() {
-                    line_.push('\n');
-                }
-                line_
-            })
-            .collect();
+        let lines = self.text();
 
         if lines.trim().is_empty() {
             return None;
@@ -231,6 +580,7 @@ impl<'a> StatefulArea<'a> {
     }
 
     fn clear_buffer(&mut self) {
+        self.textarea.move_cursor(CursorMove::Bottom);
         for _ in 0..self.textarea.lines().len() {
             self.textarea.move_cursor(CursorMove::End);
             self.textarea.delete_line_by_head();
@@ -295,25 +645,162 @@ impl<T> StatefulList<T> {
     }
 }
 
-#[derive(Debug)]
-pub struct MsgItem;
+impl StatefulList<MsgItem<'_>> {
+    pub fn page_up(&mut self, page: u16) {
+        let Some(last) = self.items.len().checked_sub(1) else {
+            return;
+        };
+        let mut i = self.state.selected().unwrap_or(last).min(last);
+        let mut scrolled = 0;
+        while i > 0 && scrolled < page as usize {
+            i -= 1;
+            scrolled += self.items[i].text.height();
+        }
+        self.state.select(Some(i));
+    }
+
+    pub fn page_down(&mut self, page: u16) {
+        let Some(last) = self.items.len().checked_sub(1) else {
+            return;
+        };
+        let mut i = self.state.selected().unwrap_or(last).min(last);
+        let mut scrolled = 0;
+        while i < last && scrolled < page as usize {
+            scrolled += self.items[i].text.height();
+            i += 1;
+        }
+        self.state.select(Some(i));
+    }
+
+    pub fn item_at(&self, area: Rect, column: u16, row: u16) -> Option<usize> {
+        if !area.contains(Position::new(column, row)) {
+            return None;
+        }
+        let mut top = area.y;
+        for (i, item) in self.items.iter().enumerate().skip(self.state.offset()) {
+            let bottom = top.saturating_add(item.text.height() as u16);
+            if row < bottom {
+                return Some(i);
+            }
+            top = bottom;
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MsgItem<'a> {
+    pub text: Text<'a>,
+    pub msg: Option<TextMessage>,
+    author: Option<(String, Color)>,
+    quote: Option<(String, String)>,
+    pub seen_by: Vec<String>,
+    rendered_content: Option<RenderedContent<'a>>,
+}
 
-impl MsgItem {
-    pub fn info_msg<'a>(msg: String, color: Color) -> Text<'a> {
+#[derive(Debug, Clone)]
+struct RenderedContent<'a> {
+    content: String,
+    pattern: String,
+    markdown: bool,
+    lines: Vec<Line<'a>>,
+}
+
+impl<'a> MsgItem<'a> {
+    const QUOTE_LEN: usize = 40;
+
+    pub fn info_msg(msg: String, color: Color) -> Self {
         let mut text = Text::from(msg);
         text.push_line("");
-        text.style(Style::new().fg(color).italic())
+        Self {
+            text: text.style(Style::new().fg(color).italic()),
+            msg: None,
+            author: None,
+            quote: None,
+            seen_by: vec![],
+            rendered_content: None,
+        }
     }
 
-    pub fn user_msg<'a>(
+    pub fn user_msg(
         text_msg: &TextMessage,
         user_id: String,
         user_color: impl Into<Color>,
+        quote: Option<(String, String)>,
         chat_style: &ChatStyle,
-        target_user: String,
+        target_user: &User,
+    ) -> Self {
+        let user_color = user_color.into();
+        let mut rendered_content = None;
+        Self {
+            text: Self::render_user_msg(
+                text_msg,
+                &user_id,
+                user_color,
+                quote.as_ref(),
+                chat_style,
+                target_user,
+                &mut rendered_content,
+            ),
+            msg: Some(text_msg.clone()),
+            author: Some((user_id, user_color)),
+            quote,
+            seen_by: vec![],
+            rendered_content,
+        }
+    }
+
+    pub fn rerender(&mut self, chat_style: &ChatStyle, target_user: &User) {
+        if let (Some(msg), Some((user_id, user_color))) = (&self.msg, &self.author) {
+            self.text = Self::render_user_msg(
+                msg,
+                user_id,
+                *user_color,
+                self.quote.as_ref(),
+                chat_style,
+                target_user,
+                &mut self.rendered_content,
+            );
+            if !self.seen_by.is_empty() {
+                let seen_line = Line::from(format!("seen by {}", self.seen_by.join(", ")))
+                    .fg(Color::Rgb(75, 75, 75))
+                    .italic()
+                    .right_aligned();
+                self.text.lines.insert(self.text.lines.len() - 1, seen_line);
+            }
+        }
+    }
+
+    pub fn quote(&self) -> Option<(String, String)> {
+        let (user_id, _) = self.author.as_ref()?;
+        let msg = self.msg.as_ref()?;
+        let content = if msg.deleted {
+            "message deleted".to_string()
+        } else {
+            let first_line = msg.content.lines().next().unwrap_or_default();
+            if first_line.chars().count() > Self::QUOTE_LEN || msg.content.lines().count() > 1 {
+                format!(
+                    "{}...",
+                    first_line.chars().take(Self::QUOTE_LEN).collect::<String>()
+                )
+            } else {
+                first_line.to_string()
+            }
+        };
+        Some((user_id.clone(), content))
+    }
+
+    fn render_user_msg(
+        text_msg: &TextMessage,
+        user_id: &str,
+        user_color: Color,
+        quote: Option<&(String, String)>,
+        chat_style: &ChatStyle,
+        target_user: &User,
+        rendered_content: &mut Option<RenderedContent<'a>>,
     ) -> Text<'a> {
-        let mut text = Text::from(Line::from(vec![
-            Span::from(user_id.clone()).style(Style::new().bold().fg(user_color.into())),
+        let mut header = vec![
+            Span::from(user_id.to_string()).style(Style::new().bold().fg(user_color)),
             Span::from(format!(" {}", {
                 if let Some(ts) = text_msg.timestamp {
                     systime_to_string(ts)
@@ -323,26 +810,135 @@ impl MsgItem {
             }))
             .fg(Color::Rgb(50, 50, 50))
             .italic(),
-        ]));
-        let content = highlight_text(
-            text_msg.content.clone(),
-            &format!(r"@{}", target_user),
-            chat_style.block.reversed().bold(),
-        );
+        ];
+        if !text_msg.edits.is_empty() && !text_msg.deleted {
+            header.push(Span::from(" (edited)").fg(Color::Rgb(50, 50, 50)).italic());
+        }
+        let mut text = Text::default();
+        if let Some((quoted_user, quoted_content)) = quote {
+            text.push_line(
+                Line::from(format!("> {}: {}", quoted_user, quoted_content))
+                    .fg(Color::Rgb(75, 75, 75))
+                    .italic(),
+            );
+        }
+        text.push_line(Line::from(header));
 
-        content
-            .lines
-            .into_iter()
-            .for_each(|line| text.push_line(line.not_bold()));
+        if text_msg.deleted {
+            text.push_line(
+                Line::from("message deleted")
+                    .fg(Color::Rgb(75, 75, 75))
+                    .italic(),
+            );
+        } else {
+            let pattern = chat_style.highlight_pattern(&target_user.id);
+            let is_cached = rendered_content.as_ref().is_some_and(|rendered| {
+                rendered.content == text_msg.content
+                    && rendered.pattern == pattern
+                    && rendered.markdown == chat_style.markdown
+            });
+            if !is_cached {
+                let lines = if chat_style.markdown {
+                    let highlight = Regex::new(&pattern).ok();
+                    markdown::render(
+                        &markdown::parse(&text_msg.content),
+                        highlight.as_ref(),
+                        chat_style.block.reversed(),
+                        chat_style.code,
+                        chat_style.light_mode,
+                    )
+                } else {
+                    highlight_text(
+                        text_msg.content.clone(),
+                        &pattern,
+                        chat_style.block.reversed().bold(),
+                    )
+                    .lines
+                    .into_iter()
+                    .map(|line| line.not_bold())
+                    .collect()
+                };
+                *rendered_content = Some(RenderedContent {
+                    content: text_msg.content.clone(),
+                    pattern,
+                    markdown: chat_style.markdown,
+                    lines,
+                });
+            }
+            if let Some(rendered) = rendered_content {
+                rendered
+                    .lines
+                    .iter()
+                    .for_each(|line| text.push_line(line.clone()));
+            }
+
+            if !text_msg.reactions.is_empty() {
+                text.push_line(Line::from(
+                    text_msg
+                        .reactions
+                        .iter()
+                        .flat_map(|reaction| {
+                            let summary =
+                                Span::from(format!("{} {}", reaction.emoji, reaction.users.len()));
+                            let summary = if reaction.users.contains(&target_user.uid) {
+                                summary.style(chat_style.block.reversed())
+                            } else {
+                                summary.fg(Color::Rgb(75, 75, 75))
+                            };
+                            [summary, Span::from(" ")]
+                        })
+                        .collect::<Vec<Span>>(),
+                ));
+            }
+        }
         text.push_line("");
         text
     }
+
+    pub fn private_msg(
+        text_msg: &TextMessage,
+        sender_id: String,
+        sender_color: impl Into<Color>,
+        recipient_id: String,
+    ) -> Self {
+        let mut text = Text::from(Line::from(vec![
+            Span::from(sender_id).style(Style::new().bold().fg(sender_color.into())),
+            Span::from(format!(" -> {}", recipient_id)).bold(),
+            Span::from(format!(" [whisper] {}", {
+                if let Some(ts) = text_msg.timestamp {
+                    systime_to_string(ts)
+                } else {
+                    "unknown timestamp".to_string()
+                }
+            }))
+            .fg(Color::Rgb(50, 50, 50))
+            .italic(),
+        ]));
+        text_msg
+            .content
+            .lines()
+            .for_each(|line| text.push_line(Line::from(line.to_string()).italic()));
+        text.push_line("");
+        Self {
+            text,
+            msg: None,
+            author: None,
+            quote: None,
+            seen_by: vec![],
+            rendered_content: None,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct ChatStyle {
     pub block: Style,
     pub msg_highlight: Style,
+    pub highlight_words: Vec<String>,
+    pub markdown: bool,
+    pub code: Style,
+    pub light_mode: bool,
+    pub search_match: Style,
 }
 
 impl ChatStyle {
@@ -350,13 +946,101 @@ impl ChatStyle {
         Self {
             block,
             msg_highlight,
+            highlight_words: vec![],
+            markdown: true,
+            code: Style::new().bg(Color::Rgb(40, 40, 40)),
+            light_mode: false,
+            search_match: Style::new().fg(Color::Black).bg(Color::Yellow),
         }
     }
+
+    pub fn highlight_pattern(&self, target_user: &str) -> String {
+        let mut patterns = vec![format!(r"@{}", regex::escape(target_user))];
+        patterns.extend(
+            self.highlight_words
+                .iter()
+                .map(|word| format!(r"(?i:\b{}\b)", regex::escape(word))),
+        );
+        patterns.join("|")
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum PopupState {
     Help,
     List,
+    Mentions,
+    Pins,
+    Emoji,
+    Completion,
     None,
 }
+
+#[cfg(test)]
+mod test {
+    use super::{MsgItem, StatefulList};
+    use ratatui::prelude::*;
+
+    fn msg_list(lines: &[usize]) -> StatefulList<MsgItem<'static>> {
+        StatefulList {
+            items: lines
+                .iter()
+                .map(|lines| MsgItem::info_msg(vec!["msg"; *lines].join("\n"), Color::White))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn paging() {
+        let mut empty = msg_list(&[]);
+        empty.page_up(10);
+        empty.page_down(10);
+        assert_eq!(empty.state.selected(), None);
+
+        let mut short = msg_list(&[1, 1, 1]);
+        short.page_up(10);
+        assert_eq!(short.state.selected(), Some(0));
+        short.page_up(10);
+        assert_eq!(short.state.selected(), Some(0));
+        short.page_down(10);
+        assert_eq!(short.state.selected(), Some(2));
+        short.page_down(10);
+        assert_eq!(short.state.selected(), Some(2));
+
+        let mut long = msg_list(&[1; 10]);
+        long.select_last();
+        long.page_up(5);
+        assert_eq!(long.state.selected(), Some(6));
+        long.page_up(5);
+        assert_eq!(long.state.selected(), Some(3));
+        long.page_up(5);
+        assert_eq!(long.state.selected(), Some(0));
+        long.page_down(5);
+        assert_eq!(long.state.selected(), Some(3));
+        long.page_down(100);
+        assert_eq!(long.state.selected(), Some(9));
+    }
+
+    #[test]
+    fn item_hit_testing() {
+        let area = Rect::new(2, 1, 20, 10);
+        assert_eq!(msg_list(&[]).item_at(area, 2, 1), None);
+
+        let mut list = msg_list(&[1, 3, 1]);
+        assert_eq!(list.item_at(area, 2, 1), Some(0));
+        assert_eq!(list.item_at(area, 21, 2), Some(0));
+        assert_eq!(list.item_at(area, 2, 3), Some(1));
+        assert_eq!(list.item_at(area, 2, 6), Some(1));
+        assert_eq!(list.item_at(area, 2, 8), Some(2));
+        assert_eq!(list.item_at(area, 2, 9), None);
+        assert_eq!(list.item_at(area, 1, 1), None);
+        assert_eq!(list.item_at(area, 2, 0), None);
+        assert_eq!(list.item_at(area, 22, 1), None);
+
+        *list.state.offset_mut() = 1;
+        assert_eq!(list.item_at(area, 2, 1), Some(1));
+        assert_eq!(list.item_at(area, 2, 5), Some(2));
+        assert_eq!(list.item_at(area, 2, 7), None);
+    }
+}
@@ -0,0 +1,178 @@
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+pub fn wrap_line(line: &str, width: usize) -> Vec<Range<usize>> {
+    let width = width.max(1);
+    let mut rows = vec![];
+    let mut row_start = 0;
+    let mut row_width = 0;
+    let mut last_break = 0;
+
+    for (i, grapheme) in line.grapheme_indices(true) {
+        let grapheme_width = grapheme.width();
+        if row_width + grapheme_width > width && i > row_start {
+            if last_break > row_start {
+                rows.push(row_start..last_break);
+                row_start = last_break;
+            } else {
+                rows.push(row_start..i);
+                row_start = i;
+            }
+            row_width = line[row_start..i].width();
+            if row_width + grapheme_width > width && i > row_start {
+                rows.push(row_start..i);
+                row_start = i;
+                row_width = 0;
+            }
+        }
+
+        row_width += grapheme_width;
+        if grapheme.chars().all(char::is_whitespace) {
+            last_break = i + grapheme.len();
+        }
+    }
+
+    rows.push(row_start..line.len());
+    rows
+}
+
+pub fn cursor_position(line: &str, width: usize, col: usize) -> (usize, usize) {
+    let offset = line
+        .char_indices()
+        .nth(col)
+        .map_or(line.len(), |(offset, _)| offset);
+    let rows = wrap_line(line, width);
+    let row = rows
+        .iter()
+        .position(|range| offset < range.end)
+        .unwrap_or(rows.len() - 1);
+
+    let x = line[rows[row].start..offset].width();
+    if x >= width.max(1) {
+        (row + 1, 0)
+    } else {
+        (row, x)
+    }
+}
+
+pub fn col_at(line: &str, width: usize, row: usize, x: usize) -> usize {
+    let rows = wrap_line(line, width);
+    if row >= rows.len() {
+        return line.chars().count();
+    }
+    let is_last_row = row + 1 >= rows.len();
+    let range = rows[row].clone();
+
+    let mut offset = range.start;
+    let mut row_width = 0;
+    for (i, grapheme) in line[range.clone()].grapheme_indices(true) {
+        let grapheme_width = grapheme.width();
+        let is_row_end = !is_last_row && range.start + i + grapheme.len() == range.end;
+        if row_width + grapheme_width > x || is_row_end {
+            offset = range.start + i;
+            break;
+        }
+        row_width += grapheme_width;
+        offset = range.start + i + grapheme.len();
+    }
+    line[..offset].chars().count()
+}
+
+pub fn visual_rows(line: &str, width: usize) -> usize {
+    let rows = wrap_line(line, width);
+    let (cursor_row, _) = cursor_position(line, width, line.chars().count());
+    rows.len().max(cursor_row + 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{col_at, cursor_position, visual_rows, wrap_line};
+    use proptest::prelude::*;
+    use unicode_segmentation::UnicodeSegmentation;
+    use unicode_width::UnicodeWidthStr;
+
+    fn mixed_text() -> impl Strategy<Value = String> {
+        prop::collection::vec(
+            prop::sample::select(vec![
+                "a", "Z", "7", "ż", "ł", "ś", "中", "文", "字", "😀", "🦀", "e\u{301}", " ", " ",
+                "-",
+            ]),
+            0..80,
+        )
+        .prop_map(|graphemes| graphemes.concat())
+    }
+
+    fn rows_text(line: &str, width: usize) -> Vec<&str> {
+        wrap_line(line, width)
+            .into_iter()
+            .map(|range| &line[range])
+            .collect()
+    }
+
+    #[test]
+    fn word_wrapping() {
+        assert_eq!(rows_text("", 10), vec![""]);
+        assert_eq!(rows_text("ala ma kota", 7), vec!["ala ma ", "kota"]);
+        assert_eq!(rows_text("zażółć gęślą", 8), vec!["zażółć ", "gęślą"]);
+        assert_eq!(rows_text("中文字符中文", 5), vec!["中文", "字符", "中文"]);
+        assert_eq!(rows_text("abcdefgh", 3), vec!["abc", "def", "gh"]);
+    }
+
+    #[test]
+    fn cursor_positions() {
+        assert_eq!(cursor_position("ala ma kota", 7, 0), (0, 0));
+        assert_eq!(cursor_position("ala ma kota", 7, 7), (1, 0));
+        assert_eq!(cursor_position("ala ma kota", 7, 11), (1, 4));
+        assert_eq!(cursor_position("中文", 4, 1), (0, 2));
+        assert_eq!(cursor_position("中文", 4, 2), (1, 0));
+        assert_eq!(visual_rows("中文", 4), 2);
+        assert_eq!(col_at("ala ma kota", 7, 1, 2), 9);
+        assert_eq!(col_at("中文字", 4, 0, 3), 1);
+    }
+
+    proptest! {
+        #[test]
+        fn rows_cover_line(line in mixed_text(), width in 2usize..40) {
+            let rows = wrap_line(&line, width);
+            prop_assert_eq!(rows.first().unwrap().start, 0);
+            prop_assert_eq!(rows.last().unwrap().end, line.len());
+            for pair in rows.windows(2) {
+                prop_assert_eq!(pair[0].end, pair[1].start);
+                prop_assert!(pair[0].start < pair[0].end);
+            }
+        }
+
+        #[test]
+        fn rows_fit_width(line in mixed_text(), width in 2usize..40) {
+            for row in rows_text(&line, width) {
+                prop_assert!(row.width() <= width || row.graphemes(true).count() == 1);
+            }
+        }
+
+        #[test]
+        fn rows_split_on_graphemes(line in mixed_text(), width in 2usize..40) {
+            let boundaries = line
+                .grapheme_indices(true)
+                .map(|(i, _)| i)
+                .chain([line.len()])
+                .collect::<Vec<usize>>();
+            for row in wrap_line(&line, width) {
+                prop_assert!(boundaries.contains(&row.start));
+                prop_assert!(boundaries.contains(&row.end));
+            }
+        }
+
+        #[test]
+        fn cursor_round_trip(line in mixed_text(), width in 2usize..40) {
+            let mut col = 0;
+            for grapheme in line.graphemes(true).chain([""]) {
+                let (row, x) = cursor_position(&line, width, col);
+                prop_assert!(x < width);
+                prop_assert!(row < visual_rows(&line, width));
+                prop_assert_eq!(col_at(&line, width, row, x), col);
+                col += grapheme.chars().count();
+            }
+        }
+    }
+}
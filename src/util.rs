@@ -12,6 +12,16 @@ use std::{
 };
 use uuid::Uuid;
 
+pub const MAX_USERNAME_LEN: usize = 64;
+
+pub fn is_valid_username(username: &str) -> bool {
+    !username.is_empty()
+        && username.chars().count() <= MAX_USERNAME_LEN
+        && !username
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control())
+}
+
 pub fn get_unique_id() -> String {
     format!("user{}", Uuid::new_v4())
 }